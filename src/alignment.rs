@@ -0,0 +1,58 @@
+/// When every planet's note fires at once again, worked out from the periods instead of watching the flashes
+use crate::model::Planet;
+use crate::simulation::off_beat;
+
+/// How far apart the triggers may be and still count as together, same as the flash window
+pub const TOLERANCE: f64 = 0.2;
+
+/// Give up after 100 days, irrational-ish period ratios can take practically forever to line up
+pub const HORIZON: f64 = 100.0 * 24.0 * 60.0 * 60.0;
+/// Most beats of the slowest planet checked, or a system of really short periods would stall startup
+pub const MAX_CHECKS: u64 = 1_000_000;
+
+/// First time after the start where every period has a trigger within `tolerance`, or `None` if that's past `horizon`
+/// or [`MAX_CHECKS`] beats away. The slowest planet is the rarest trigger, so only its beats need checking.
+pub fn alignment_time(periods: &[f64], tolerance: f64, horizon: f64) -> Option<f64> {
+    let slowest = periods.iter().copied().fold(0.0, f64::max);
+    if slowest <= 0.0 {
+        return None;
+    }
+    (1..=MAX_CHECKS)
+        .map(|n| n as f64 * slowest)
+        .take_while(|&t| t <= horizon)
+        .find(|&t| {
+            periods
+                .iter()
+                .all(|&period| off_beat(period, t) <= tolerance)
+        })
+}
+
+/// How far ahead [`predicted_duration`] looks: [`HORIZON`], or less if the slowest planet is so quick
+/// that it'd take more than [`MAX_CHECKS`] of its beats to get there
+pub fn horizon(planets: &[Planet]) -> f64 {
+    let slowest = planets.iter().map(Planet::period).fold(0.0, f64::max);
+    HORIZON.min(slowest * MAX_CHECKS as f64)
+}
+
+/// How long the piece lasts, from the start until the planets align again
+pub fn predicted_duration(planets: &[Planet]) -> Option<f64> {
+    let periods: Vec<f64> = planets.iter().map(|planet| planet.period()).collect();
+    alignment_time(&periods, TOLERANCE, horizon(planets))
+}
+
+/// The last piece time a note goes out when the piece ends at `end`, the chord the planets align on can be up to [`TOLERANCE`] late
+pub fn last_note(end: f64) -> f64 {
+    end + TOLERANCE
+}
+
+/// `1d 03:25:07` style, the alignments are usually way too long for plain seconds
+pub fn format_duration(seconds: f64) -> String {
+    let total = seconds.round() as u64;
+    let (days, rest) = (total / 86_400, total % 86_400);
+    let clock = format!("{:02}:{:02}:{:02}", rest / 3600, rest / 60 % 60, rest % 60);
+    if days > 0 {
+        format!("{}d {}", days, clock)
+    } else {
+        clock
+    }
+}
@@ -0,0 +1,107 @@
+/// Realtime playback: runs the [`Sequencer`] inside SDL_mixer's post-mix callback so every note
+/// starts on the exact sample it's due instead of whenever the render loop gets around to it
+use std::os::raw::{c_int, c_void};
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use sdl2::mixer::AUDIO_F32;
+
+use crate::clock::Clock;
+use crate::sequencer::{Sequencer, CHANNELS, SAMPLE_RATE};
+
+/// Sequencer state shared between the audio callback and the render loop
+pub struct Playback {
+    pub sequencer: Sequencer,
+    /// false keeps the sequencer running for its triggers but leaves it out of the mix
+    pub audio: bool,
+    /// the clock at the start of the buffer the callback last handed to SDL, and when it did
+    block_clock: Clock,
+    filled_at: Instant,
+}
+
+impl Playback {
+    /// Seconds into the piece, interpolated between callbacks so the orbits don't step with the buffer size
+    pub fn elapsed(&self) -> f64 {
+        self.block_clock.seconds()
+            + self.block_clock.rate() * self.filled_at.elapsed().as_secs_f64()
+    }
+
+    /// Restarts the interpolation from `seconds`, at whatever speed the sequencer runs now
+    fn resync(&mut self, seconds: f64) {
+        self.block_clock = *self.sequencer.clock();
+        self.block_clock.seek(seconds);
+        self.filled_at = Instant::now();
+    }
+
+    /// Jumps to `seconds` into the piece, for both the notes and the orbits
+    pub fn seek(&mut self, seconds: f64) {
+        self.sequencer.seek(seconds);
+        self.resync(seconds);
+    }
+
+    pub fn set_speed(&mut self, speed: f64) {
+        let now = self.elapsed();
+        self.sequencer.set_speed(speed);
+        self.resync(now);
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        let now = self.elapsed();
+        self.sequencer.set_paused(paused);
+        self.resync(now);
+    }
+}
+
+static INSTALLED: AtomicPtr<Mutex<Playback>> = AtomicPtr::new(std::ptr::null_mut());
+
+unsafe extern "C" fn postmix(udata: *mut c_void, stream: *mut u8, len: c_int) {
+    let playback = &*(udata as *const Mutex<Playback>);
+    let out = std::slice::from_raw_parts_mut(stream as *mut f32, len as usize / 4);
+    if let Ok(mut playback) = playback.lock() {
+        playback.block_clock = *playback.sequencer.clock();
+        playback.sequencer.fill(out);
+        if !playback.audio {
+            out.fill(0.0);
+        }
+        playback.filled_at = Instant::now();
+    }
+}
+
+/// Starts feeding `sequencer` into the mixer output. The mixer must already be opened as 44.1 kHz stereo f32.
+pub fn install(sequencer: Sequencer) -> Result<Arc<Mutex<Playback>>, String> {
+    let (frequency, format, channels) = sdl2::mixer::query_spec()?;
+    if frequency != SAMPLE_RATE as i32 || format != AUDIO_F32 || channels != CHANNELS as i32 {
+        return Err(format!(
+            "audio device opened as {} Hz, format {:#x}, {} channels; the sequencer needs {} Hz f32 stereo",
+            frequency, format, channels, SAMPLE_RATE
+        ));
+    }
+
+    let playback = Arc::new(Mutex::new(Playback {
+        sequencer,
+        audio: true,
+        block_clock: Clock::default(),
+        filled_at: Instant::now(),
+    }));
+    uninstall();
+    let raw = Arc::into_raw(Arc::clone(&playback)) as *mut Mutex<Playback>;
+    INSTALLED.store(raw, Ordering::SeqCst);
+    unsafe {
+        sdl2::sys::mixer::Mix_SetPostMix(Some(postmix), raw as *mut c_void);
+    }
+    Ok(playback)
+}
+
+/// Removes the post-mix hook and drops the callback's reference to the playback state
+pub fn uninstall() {
+    let raw = INSTALLED.swap(std::ptr::null_mut(), Ordering::SeqCst);
+    if raw.is_null() {
+        return;
+    }
+    unsafe {
+        // Mix_SetPostMix locks the audio device, so the callback is done with `raw` once this returns
+        sdl2::sys::mixer::Mix_SetPostMix(None, std::ptr::null_mut());
+        drop(Arc::from_raw(raw));
+    }
+}
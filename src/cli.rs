@@ -0,0 +1,335 @@
+/// Command line parsing, hand rolled since there's only a handful of flags
+use std::path::PathBuf;
+
+use crate::clock::{Clock, MAX_SPEED, MIN_SPEED};
+use crate::controls::View;
+use crate::events::{EventLog, LogFormat, Outputs};
+use crate::live_midi::LiveMidi;
+use crate::mixer::Spatial;
+use crate::model::{solar_system_at, Planet};
+use crate::osc::{self, OscSender};
+use crate::pattern::{self, Blend, Pattern};
+use crate::synth;
+use crate::system::load_system;
+use crate::tempo::{Tempo, ORIGINAL_TEMPO};
+use crate::tuning::{self, Tuning};
+use crate::voices::Polyphony;
+
+const COMMANDS: [&str; 4] = ["multi", "wav", "render", "export-midi"];
+
+/// How the players and exports play the planets, everything from the flags besides the planets themselves
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub clock: Clock,
+    pub spatial: Spatial,
+    pub polyphony: Polyphony,
+    pub view: View,
+    pub pattern: Option<Pattern>,
+}
+
+#[derive(Debug, Default)]
+pub struct Args {
+    /// `multi`, `wav`, `render` or `export-midi`, nothing means the single threaded player
+    pub command: Option<String>,
+    /// whatever follows the command, e.g. the output path and duration
+    pub positional: Vec<String>,
+    /// `--system <file>`, a TOML/JSON planetary system to play instead of the solar system
+    pub system: Option<PathBuf>,
+    /// `--float`, write 32-bit float WAVs
+    pub float: bool,
+    /// `--start <seconds>`, where in the piece the player starts
+    pub start: Option<f64>,
+    /// `--speed <factor>`, how fast the player runs through the piece
+    pub speed: Option<f64>,
+    /// `--tempo <bpm>` is earth's tempo with the rest of the solar system following,
+    /// `--length <seconds>` how long one revolution of the slowest planet takes
+    pub tempo: Option<Tempo>,
+    /// `--synth`, play the embedded notes on the built-in synth instead
+    pub synth: bool,
+    /// `--tuning <harmonic|equal|just|kepler>`, re-pitch the planets
+    pub tuning: Option<Tuning>,
+    /// `--fundamental <Hz>`, what `--tuning` is built on
+    pub fundamental: Option<f64>,
+    /// `--spatial` pans notes along with their planet, `--distance` also makes far planets quieter
+    pub spatial: Spatial,
+    /// `--voices <n>`, `--planet-voices <n>` and `--steal <oldest|quietest|planet>`
+    pub polyphony: Polyphony,
+    /// `--orbits`, `--labels`, `--trails`, `--sun`, `--sizes` and `--no-hud`, what's drawn to begin with
+    pub view: View,
+    /// `--pattern <earth-venus,...>`, planet pairs to draw a mandala pattern between
+    pub pattern: Option<String>,
+    /// `--pattern-interval <seconds>`, piece time between chords
+    pub pattern_interval: Option<f64>,
+    /// `--pattern-fade <0..1>`, how much of the pattern fades with every chord
+    pub pattern_fade: Option<f32>,
+    /// `--pattern-blend <add|alpha>`
+    pub pattern_blend: Blend,
+    /// `--log <file>`, where the players write every note they trigger
+    pub log: Option<PathBuf>,
+    /// `--log-format <csv|jsonl>`, going by the log's extension if not given
+    pub log_format: Option<LogFormat>,
+    /// `--midi-out <port>`, a MIDI output port to play the notes on as well
+    pub midi_out: Option<String>,
+    /// `--midi-only`, play the notes on `--midi-out` instead of the samples
+    pub midi_only: bool,
+    /// `--osc <host:port>`, where to send OSC messages for every trigger and planet positions
+    pub osc: Option<String>,
+    /// `--osc-interval <seconds>`, real time between position updates
+    pub osc_interval: Option<f64>,
+}
+
+/// The voice count after a flag, at least one
+fn voices(flag: &str, value: Option<String>) -> Result<usize, String> {
+    match number(flag, value)? {
+        n if n >= 1.0 && n.fract() == 0.0 => Ok(n as usize),
+        n => Err(format!(
+            "{} needs a whole number of voices, got {}",
+            flag, n
+        )),
+    }
+}
+
+/// The number after a flag
+fn number(flag: &str, value: Option<String>) -> Result<f64, String> {
+    let value = value.ok_or(format!("{} needs a number", flag))?;
+    match value.parse::<f64>() {
+        Ok(number) if number.is_finite() => Ok(number),
+        _ => Err(format!("{} needs a number, got '{}'", flag, value)),
+    }
+}
+
+impl Args {
+    /// Parses the arguments after the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--system" => {
+                    parsed.system = Some(args.next().ok_or("--system needs a file")?.into())
+                }
+                "--float" => parsed.float = true,
+                "--synth" => parsed.synth = true,
+                "--tuning" => {
+                    parsed.tuning = Some(args.next().ok_or("--tuning needs a name")?.parse()?)
+                }
+                "--fundamental" => match number("--fundamental", args.next())? {
+                    hz if hz > 0.0 => parsed.fundamental = Some(hz),
+                    hz => return Err(format!("--fundamental must be positive, got {}", hz)),
+                },
+                "--spatial" => parsed.spatial = parsed.spatial.max(Spatial::Pan),
+                "--distance" => parsed.spatial = Spatial::Distance,
+                "--voices" => parsed.polyphony.voices = voices("--voices", args.next())?,
+                "--planet-voices" => {
+                    parsed.polyphony.per_planet = Some(voices("--planet-voices", args.next())?)
+                }
+                "--steal" => {
+                    parsed.polyphony.steal = args.next().ok_or("--steal needs a policy")?.parse()?
+                }
+                "--orbits" => parsed.view.orbits = true,
+                "--labels" => parsed.view.labels = true,
+                "--trails" => parsed.view.trails = true,
+                "--sun" => parsed.view.sun = true,
+                "--sizes" => parsed.view.sizes = true,
+                "--no-hud" => parsed.view.hud = false,
+                "--pattern" => {
+                    parsed.pattern = Some(args.next().ok_or("--pattern needs planet pairs")?)
+                }
+                "--pattern-interval" => match number("--pattern-interval", args.next())? {
+                    seconds if seconds.is_finite() && seconds > 0.0 => {
+                        parsed.pattern_interval = Some(seconds)
+                    }
+                    seconds => {
+                        return Err(format!(
+                            "--pattern-interval must be positive, got {}",
+                            seconds
+                        ))
+                    }
+                },
+                "--pattern-fade" => match number("--pattern-fade", args.next())? {
+                    fade if (0.0..=1.0).contains(&fade) => parsed.pattern_fade = Some(fade as f32),
+                    fade => return Err(format!("--pattern-fade must be 0 to 1, got {}", fade)),
+                },
+                "--pattern-blend" => {
+                    parsed.pattern_blend =
+                        args.next().ok_or("--pattern-blend needs a mode")?.parse()?
+                }
+                "--log" => parsed.log = Some(args.next().ok_or("--log needs a file")?.into()),
+                "--log-format" => {
+                    parsed.log_format =
+                        Some(args.next().ok_or("--log-format needs a format")?.parse()?)
+                }
+                "--midi-out" => {
+                    parsed.midi_out = Some(args.next().ok_or("--midi-out needs a port name")?)
+                }
+                "--midi-only" => parsed.midi_only = true,
+                "--osc" => parsed.osc = Some(args.next().ok_or("--osc needs a host:port")?),
+                "--osc-interval" => match number("--osc-interval", args.next())? {
+                    seconds if seconds.is_finite() && seconds > 0.0 => {
+                        parsed.osc_interval = Some(seconds)
+                    }
+                    seconds => {
+                        return Err(format!("--osc-interval must be positive, got {}", seconds))
+                    }
+                },
+                "--start" => match number("--start", args.next())? {
+                    seconds if seconds >= 0.0 => parsed.start = Some(seconds),
+                    seconds => return Err(format!("--start can't be negative, got {}", seconds)),
+                },
+                "--speed" => match number("--speed", args.next())? {
+                    speed if (MIN_SPEED..=MAX_SPEED).contains(&speed) => parsed.speed = Some(speed),
+                    speed => {
+                        return Err(format!(
+                            "--speed must be {} to {}, got {}",
+                            MIN_SPEED, MAX_SPEED, speed
+                        ))
+                    }
+                },
+                "--tempo" | "--length" if parsed.tempo.is_some() => {
+                    let earlier = match parsed.tempo {
+                        Some(Tempo::Length(_)) => "--length",
+                        _ => "--tempo",
+                    };
+                    return Err(if earlier == arg {
+                        format!("{} was given twice", arg)
+                    } else {
+                        "--tempo and --length can't be used together".to_string()
+                    });
+                }
+                "--tempo" => match number("--tempo", args.next())? {
+                    bpm if bpm > 0.0 => parsed.tempo = Some(Tempo::earth(bpm as f32)),
+                    bpm => return Err(format!("--tempo must be positive, got {}", bpm)),
+                },
+                "--length" => match number("--length", args.next())? {
+                    seconds if seconds > 0.0 => parsed.tempo = Some(Tempo::Length(seconds as f32)),
+                    seconds => return Err(format!("--length must be positive, got {}", seconds)),
+                },
+                flag if flag.starts_with("--") => return Err(format!("unknown flag {}", flag)),
+                command
+                    if parsed.command.is_none()
+                        && parsed.positional.is_empty()
+                        && COMMANDS.contains(&command) =>
+                {
+                    parsed.command = Some(arg)
+                }
+                _ => parsed.positional.push(arg),
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// The planets to play, from `--system` if given
+    pub fn planets(&self) -> Result<Vec<Planet>, String> {
+        let mut planets = match &self.system {
+            Some(path) => {
+                let mut planets = load_system(path)?;
+                match self.tempo {
+                    // system files come with their own tempos, so only their overall length can change
+                    Some(Tempo::Length(seconds)) => {
+                        let periods: Vec<f32> =
+                            planets.iter().map(|planet| planet.period() as f32).collect();
+                        let bpms = Tempo::Length(seconds).bpms(&periods);
+                        for (planet, bpm) in planets.iter_mut().zip(bpms) {
+                            planet.bpm = bpm;
+                        }
+                    }
+                    Some(Tempo::Reference { .. }) => {
+                        return Err(
+                            "--tempo sets earth's tempo in the solar system, use --length with --system"
+                                .to_string(),
+                        )
+                    }
+                    None => {}
+                }
+                planets
+            }
+            None => solar_system_at(self.tempo.unwrap_or(ORIGINAL_TEMPO)),
+        };
+        if self.synth {
+            for planet in &mut planets {
+                planet.sample = planet.sample.synthesized();
+            }
+        }
+        if self.tuning.is_some() || self.fundamental.is_some() {
+            let fundamental = self.fundamental.unwrap_or(synth::FUNDAMENTAL as f64);
+            tuning::retune(
+                &mut planets,
+                self.tuning.unwrap_or(Tuning::Harmonic),
+                fundamental,
+            );
+        }
+        // a sample that only fails once the player is up would take the window down with it
+        for planet in &planets {
+            planet
+                .sample
+                .pcm()
+                .map_err(|e| format!("{}'s sample: {}", planet.name, e))?;
+        }
+        Ok(planets)
+    }
+
+    /// The mandala pattern from `--pattern` and friends, if one was asked for
+    pub fn pattern(&self, planets: &[Planet]) -> Result<Option<Pattern>, String> {
+        let Some(spec) = &self.pattern else {
+            return Ok(None);
+        };
+        Ok(Some(Pattern {
+            pairs: Pattern::pairs(spec, planets)?,
+            interval: self.pattern_interval.unwrap_or(pattern::DEFAULT_INTERVAL),
+            fade: self.pattern_fade.unwrap_or(0.0),
+            blend: self.pattern_blend,
+        }))
+    }
+
+    /// The event log from `--log` and `--log-format`, created as soon as this is called
+    pub fn event_log(&self, planets: &[Planet]) -> Result<Option<EventLog>, String> {
+        let Some(path) = &self.log else {
+            return Ok(None);
+        };
+        let format = self.log_format.unwrap_or_else(|| LogFormat::for_path(path));
+        EventLog::create(path, format, planets).map(Some)
+    }
+
+    /// Where the players' notes go besides the speakers, opening the log file, MIDI port and OSC socket
+    pub fn outputs(&self, planets: &[Planet]) -> Result<Outputs, String> {
+        if self.midi_only && self.midi_out.is_none() {
+            return Err("--midi-only needs a port from --midi-out".to_string());
+        }
+        Ok(Outputs {
+            log: self.event_log(planets)?,
+            midi: self
+                .midi_out
+                .as_deref()
+                .map(|port| LiveMidi::connect(port, planets))
+                .transpose()?,
+            osc: self
+                .osc
+                .as_deref()
+                .map(|target| {
+                    let interval = self.osc_interval.unwrap_or(osc::DEFAULT_INTERVAL);
+                    OscSender::connect(target, interval, planets)
+                })
+                .transpose()?,
+            audio: !self.midi_only,
+        })
+    }
+
+    /// Where and how fast the player starts, from `--start` and `--speed`
+    pub fn clock(&self) -> Clock {
+        let mut clock = Clock::default();
+        clock.seek(self.start.unwrap_or(0.0));
+        clock.set_speed(self.speed.unwrap_or(1.0));
+        clock
+    }
+
+    /// The [`Settings`] for playing `planets`
+    pub fn settings(&self, planets: &[Planet]) -> Result<Settings, String> {
+        Ok(Settings {
+            clock: self.clock(),
+            spatial: self.spatial,
+            polyphony: self.polyphony,
+            view: self.view,
+            pattern: self.pattern(planets)?,
+        })
+    }
+}
@@ -0,0 +1,84 @@
+/// Virtual piece clock: where in the piece we are, how fast it runs and whether it's paused.
+/// The sequencer advances it frame by frame, the renderers read it back, so seeking or speeding up moves both.
+use crate::sequencer::SAMPLE_RATE;
+
+/// Slowest and fastest speed the keyboard can reach
+pub const MIN_SPEED: f64 = 1.0 / 16.0;
+/// past this every planet but pluto turns into a drone and the voices pile up
+pub const MAX_SPEED: f64 = 256.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Clock {
+    /// piece time in frames, fractional once the speed isn't a whole number
+    position: f64,
+    speed: f64,
+    paused: bool,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self {
+            position: 0.0,
+            speed: 1.0,
+            paused: false,
+        }
+    }
+}
+
+impl Clock {
+    /// Seconds into the piece
+    pub fn seconds(&self) -> f64 {
+        self.position / SAMPLE_RATE as f64
+    }
+
+    /// Piece time in frames
+    pub fn position(&self) -> f64 {
+        self.position
+    }
+
+    /// Jumps to `seconds` into the piece, clamped to the start
+    pub fn seek(&mut self, seconds: f64) {
+        self.position = (seconds * SAMPLE_RATE as f64).max(0.0);
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Piece seconds per real second, clamped to [`MIN_SPEED`]..=[`MAX_SPEED`]
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// How fast piece time moves compared to real time right now, zero while paused
+    pub fn rate(&self) -> f64 {
+        if self.paused {
+            0.0
+        } else {
+            self.speed
+        }
+    }
+
+    /// Moves the clock along by `frames` output frames
+    pub fn advance(&mut self, frames: u64) {
+        self.position += frames as f64 * self.rate();
+    }
+
+    /// Output frame, counted from now, on which piece frame `due` is reached; `None` if that's `frames` or more away
+    pub fn offset_of(&self, due: u64, frames: u64) -> Option<u64> {
+        let rate = self.rate();
+        if rate == 0.0 {
+            return None;
+        }
+        let offset = ((due as f64 - self.position) / rate).ceil().max(0.0) as u64;
+        (offset < frames).then_some(offset)
+    }
+}
@@ -0,0 +1,144 @@
+/// Keyboard controls, shared by the single and multi threaded players
+use sdl2::keyboard::{Keycode, Mod};
+
+/// How far the arrow keys seek, in seconds, and with shift held
+pub const SEEK_STEP: f64 = 10.0;
+pub const SEEK_STEP_LARGE: f64 = 600.0;
+/// Master volume change per up/down press
+pub const VOLUME_STEP: f32 = 0.1;
+/// Gain and pan change of the selected planet's strip per press
+pub const GAIN_STEP: f32 = 0.1;
+pub const PAN_STEP: f32 = 0.1;
+
+const PLANET_KEYS: [Keycode; 9] = [
+    Keycode::Num1,
+    Keycode::Num2,
+    Keycode::Num3,
+    Keycode::Num4,
+    Keycode::Num5,
+    Keycode::Num6,
+    Keycode::Num7,
+    Keycode::Num8,
+    Keycode::Num9,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    TogglePause,
+    /// seconds to jump, negative goes back
+    Seek(f64),
+    /// back to the start of the piece
+    Restart,
+    /// multiplies the speed
+    Speed(f64),
+    /// added to the master volume
+    Volume(f32),
+    Mute(usize),
+    Solo(usize),
+    /// moves the planet selection along, negative goes back
+    Select(isize),
+    /// added to the selected planet's gain
+    Gain(f32),
+    /// added to the selected planet's pan
+    Pan(f32),
+    /// cycles spatial panning off, on and on with distance
+    Spatial,
+    ToggleOrbits,
+    ToggleLabels,
+    ToggleTrails,
+    ToggleSun,
+    /// planets drawn to scale or all the same size
+    ToggleSizes,
+    TogglePattern,
+    ToggleHud,
+    ToggleFullscreen,
+}
+
+/// Space pauses, left/right seek (further with shift), home or r restarts, `[`/`]` halve/double the speed,
+/// up/down change the volume, 1-9 mute a planet (solo with shift), tab selects the next planet (previous with shift)
+/// and `-`/`=` and `,`/`.` change its gain and pan, o, l, t, s and d toggle the orbits, labels,
+/// trails, sun and planet sizes, m the mandala pattern, p cycles spatial panning, h toggles the HUD and f fullscreen
+pub fn action(key: Keycode, keymod: Mod) -> Option<Action> {
+    let shift = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+    let step = if shift { SEEK_STEP_LARGE } else { SEEK_STEP };
+    let action = match key {
+        Keycode::Space => Action::TogglePause,
+        Keycode::Left => Action::Seek(-step),
+        Keycode::Right => Action::Seek(step),
+        Keycode::Home | Keycode::R => Action::Restart,
+        Keycode::LeftBracket => Action::Speed(0.5),
+        Keycode::RightBracket => Action::Speed(2.0),
+        Keycode::Up => Action::Volume(VOLUME_STEP),
+        Keycode::Down => Action::Volume(-VOLUME_STEP),
+        Keycode::Tab => Action::Select(if shift { -1 } else { 1 }),
+        Keycode::Minus => Action::Gain(-GAIN_STEP),
+        Keycode::Equals => Action::Gain(GAIN_STEP),
+        Keycode::Comma => Action::Pan(-PAN_STEP),
+        Keycode::Period => Action::Pan(PAN_STEP),
+        Keycode::O => Action::ToggleOrbits,
+        Keycode::L => Action::ToggleLabels,
+        Keycode::T => Action::ToggleTrails,
+        Keycode::S => Action::ToggleSun,
+        Keycode::D => Action::ToggleSizes,
+        Keycode::M => Action::TogglePattern,
+        Keycode::P => Action::Spatial,
+        Keycode::H => Action::ToggleHud,
+        Keycode::F => Action::ToggleFullscreen,
+        _ => {
+            let planet = PLANET_KEYS.iter().position(|&k| k == key)?;
+            if shift {
+                Action::Solo(planet)
+            } else {
+                Action::Mute(planet)
+            }
+        }
+    };
+    Some(action)
+}
+
+/// What gets drawn besides the planets themselves
+#[derive(Debug, Clone, Copy)]
+pub struct View {
+    pub orbits: bool,
+    pub labels: bool,
+    /// a fading streak behind every planet
+    pub trails: bool,
+    pub sun: bool,
+    /// planets scaled by their real diameter
+    pub sizes: bool,
+    /// the mandala pattern, if there is one
+    pub pattern: bool,
+    pub hud: bool,
+}
+
+impl Default for View {
+    /// Just the planets, the HUD and the pattern if one was asked for
+    fn default() -> Self {
+        Self {
+            orbits: false,
+            labels: false,
+            trails: false,
+            sun: false,
+            sizes: false,
+            pattern: true,
+            hud: true,
+        }
+    }
+}
+
+impl View {
+    /// Handles the drawing toggles, handing every other action back
+    pub fn apply(&mut self, action: Action) -> Option<Action> {
+        match action {
+            Action::ToggleOrbits => self.orbits = !self.orbits,
+            Action::ToggleLabels => self.labels = !self.labels,
+            Action::ToggleTrails => self.trails = !self.trails,
+            Action::ToggleSun => self.sun = !self.sun,
+            Action::ToggleSizes => self.sizes = !self.sizes,
+            Action::TogglePattern => self.pattern = !self.pattern,
+            Action::ToggleHud => self.hud = !self.hud,
+            _ => return Some(action),
+        }
+        None
+    }
+}
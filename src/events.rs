@@ -0,0 +1,200 @@
+/// Event log of every note the players trigger, as CSV or JSON Lines, for checking timing after the fact
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::live_midi::LiveMidi;
+use crate::model::Planet;
+use crate::osc::OscSender;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    #[default]
+    Csv,
+    /// one JSON object per line
+    Jsonl,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "csv" => Ok(LogFormat::Csv),
+            "jsonl" | "json" | "ndjson" => Ok(LogFormat::Jsonl),
+            _ => Err(format!(
+                "unknown log format '{}', expected csv or jsonl",
+                name
+            )),
+        }
+    }
+}
+
+impl LogFormat {
+    /// Going by the file's extension, CSV unless it says JSON
+    pub fn for_path(path: &Path) -> Self {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| extension.parse().ok())
+            .unwrap_or_default()
+    }
+}
+
+/// One note trigger
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoteEvent {
+    pub planet: usize,
+    /// 0 is the note right at the start
+    pub revolution: u64,
+    /// piece seconds the note was due
+    pub scheduled: f64,
+    /// piece seconds it actually went out
+    pub actual: f64,
+    /// the voice or mixer channel it got, `None` if it was dropped or muted
+    pub voice: Option<usize>,
+    /// piece seconds per real second at the time, so live MIDI can end the note when the sample would
+    pub speed: f64,
+}
+
+/// A [`NoteEvent`] as it's written out
+#[derive(Serialize)]
+struct Line<'a> {
+    planet: usize,
+    name: &'a str,
+    revolution: u64,
+    scheduled: f64,
+    actual: f64,
+    voice: Option<usize>,
+}
+
+pub struct EventLog {
+    out: BufWriter<File>,
+    format: LogFormat,
+    names: Vec<String>,
+}
+
+impl EventLog {
+    /// Creates or truncates `path`, CSV gets its header right away
+    pub fn create(path: &Path, format: LogFormat, planets: &[Planet]) -> Result<Self, String> {
+        let file = File::create(path)
+            .map_err(|e| format!("can't create event log {}: {}", path.display(), e))?;
+        let mut log = Self {
+            out: BufWriter::new(file),
+            format,
+            names: planets.iter().map(|planet| planet.name.clone()).collect(),
+        };
+        if format == LogFormat::Csv {
+            writeln!(log.out, "planet,name,revolution,scheduled,actual,voice")
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(log)
+    }
+
+    pub fn write(&mut self, event: &NoteEvent) -> Result<(), String> {
+        let name = self.names.get(event.planet).map_or("", String::as_str);
+        match self.format {
+            LogFormat::Csv => writeln!(
+                self.out,
+                "{},{},{},{:.6},{:.6},{}",
+                event.planet,
+                csv_field(name),
+                event.revolution,
+                event.scheduled,
+                event.actual,
+                event
+                    .voice
+                    .map(|voice| voice.to_string())
+                    .unwrap_or_default()
+            )
+            .map_err(|e| e.to_string()),
+            LogFormat::Jsonl => {
+                let line = Line {
+                    planet: event.planet,
+                    name,
+                    revolution: event.revolution,
+                    scheduled: event.scheduled,
+                    actual: event.actual,
+                    voice: event.voice,
+                };
+                serde_json::to_writer(&mut self.out, &line).map_err(|e| e.to_string())?;
+                writeln!(self.out).map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// Writes out whatever's buffered, the players call it on their way out
+    pub fn flush(&mut self) -> Result<(), String> {
+        self.out.flush().map_err(|e| e.to_string())
+    }
+}
+
+/// Quotes a field if it has anything CSV would trip over
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Everywhere the players send their notes besides the speakers
+pub struct Outputs {
+    pub log: Option<EventLog>,
+    pub midi: Option<LiveMidi>,
+    pub osc: Option<OscSender>,
+    /// false leaves the samples out, for when the notes are only there to drive MIDI
+    pub audio: bool,
+}
+
+impl Default for Outputs {
+    fn default() -> Self {
+        Self {
+            log: None,
+            midi: None,
+            osc: None,
+            audio: true,
+        }
+    }
+}
+
+impl Outputs {
+    /// Whether anything wants to hear about the notes
+    pub fn listening(&self) -> bool {
+        self.log.is_some() || self.midi.is_some() || self.osc.is_some()
+    }
+
+    /// Passes `note` on. `audible` says whether its planet is muted (or another one soloed) on the mixer, which only MIDI cares about.
+    pub fn send(&mut self, note: &NoteEvent, audible: bool) -> Result<(), String> {
+        if let Some(midi) = self.midi.as_ref().filter(|_| audible) {
+            midi.play(note);
+        }
+        if let Some(osc) = &self.osc {
+            osc.trigger(note);
+        }
+        if let Some(log) = &mut self.log {
+            log.write(note)?;
+        }
+        Ok(())
+    }
+
+    /// Ends the MIDI notes still sounding, MIDI can't hold a note where it is like the samples do on pause
+    pub fn silence(&self) {
+        if let Some(midi) = &self.midi {
+            midi.silence();
+        }
+    }
+
+    /// Writes out the rest of the log and lets go of the MIDI port
+    pub fn close(self) -> Result<(), String> {
+        if let Some(midi) = self.midi {
+            midi.close();
+        }
+        match self.log {
+            Some(mut log) => log.flush(),
+            None => Ok(()),
+        }
+    }
+}
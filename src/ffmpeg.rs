@@ -38,19 +38,19 @@ impl Default for FFMpegStatus {
     }
 }
 
-/// https://gist.github.com/edwardstock/90b41d4d53af4c32853073865a319222 thanks edward!  
-/// ## Usable named groups
-/// - `nframe`
-/// - `nfps`
-/// - `nq`
-/// - `nsize`
-/// - `ssize`
-/// - `sduration`
-/// - `nbitrate`
-/// - `sbitrate`
-/// - `ndup`
-/// - `ndrop`
-/// - `nspeed`
+// https://gist.github.com/edwardstock/90b41d4d53af4c32853073865a319222 thanks edward!
+// ## Usable named groups
+// - `nframe`
+// - `nfps`
+// - `nq`
+// - `nsize`
+// - `ssize`
+// - `sduration`
+// - `nbitrate`
+// - `sbitrate`
+// - `ndup`
+// - `ndrop`
+// - `nspeed`
 // const REGEX_IS_FUCKING_HIDEOUS: &str = "frame=\\s*(?<nframe>[0-9]+)\\s+fps=\\s*(?<nfps>[0-9\\.]+)\\s+q=(?<nq>[0-9\\.-]+)\\s+(L?)\\s*size=\\s*(?<nsize>[0-9]+)(?<ssize>kB|mB|b)?\\s*time=\\s*(?<sduration>[0-9\\:\\.]+)\\s*bitrate=\\s*(?<nbitrate>[0-9\\.]+)(?<sbitrate>bits\\/s|mbits\\/s|kbits\\/s)?.*(dup=(?<ndup>\\d+)\\s*)?(drop=(?<ndrop>\\d+)\\s*)?speed=\\s*(?<nspeed>[0-9\\.]+)x";
 
 fn parsery(time: &str) -> std::time::Duration {
@@ -69,7 +69,7 @@ fn parsery(time: &str) -> std::time::Duration {
 
 impl VideoRecorder {
     /// Spawns new instance of FFMpeg with out file, size and FPS
-    pub fn new(out: &str, width: u32, height: u32, fps: u32) -> Self {
+    pub fn new(out: &str, width: u32, height: u32, fps: u32) -> Result<Self, String> {
         let mut ffmpeg_cmd = std::process::Command::new("ffmpeg")
             .args([
                 "-hide_banner",
@@ -97,7 +97,7 @@ impl VideoRecorder {
             .stdin(std::process::Stdio::piped())
             .stdout(std::process::Stdio::piped())
             .spawn()
-            .expect("FFMpeg failed to start");
+            .map_err(|e| format!("FFMpeg failed to start: {}", e))?;
         let (tx, rx) = std::sync::mpsc::channel();
         let output_lines = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
         let ol_cloned = std::sync::Arc::clone(&output_lines);
@@ -112,22 +112,23 @@ impl VideoRecorder {
                     .expect("Failed to send FFMpeg line to main thread")
             }
         });
-        Self {
+        Ok(Self {
             ffmpeg: ffmpeg_cmd,
             status_receiver: rx,
             frame_count: 0,
-        }
+        })
     }
 
     /// Function for passing the frames to FFMpeg. This doesn't cost a lot performance.
-    pub fn process_frame(&mut self, frame: Vec<u8>) {
+    pub fn process_frame(&mut self, frame: Vec<u8>) -> Result<(), String> {
         self.ffmpeg
             .stdin
             .as_mut()
-            .unwrap()
+            .ok_or("FFMpeg's input is already closed")?
             .write_all(frame.as_slice())
-            .expect("Pipe is closed.");
+            .map_err(|e| format!("FFMpeg stopped taking video: {}", e))?;
         self.frame_count += 1;
+        Ok(())
     }
 
     pub fn get_render_status(&mut self) -> Option<FFMpegStatus> {
@@ -199,6 +200,16 @@ impl VideoRecorder {
     }
 }
 
+impl Drop for VideoRecorder {
+    /// An unfinished recording (one that never got to [`VideoRecorder::done`]) isn't left running in the background
+    fn drop(&mut self) {
+        if self.ffmpeg.stdin.is_some() {
+            let _ = self.ffmpeg.kill();
+            let _ = self.ffmpeg.wait();
+        }
+    }
+}
+
 pub struct AudioRecorder {
     ffmpeg: std::process::Child,
     status_receiver: std::sync::mpsc::Receiver<String>,
@@ -206,7 +217,7 @@ pub struct AudioRecorder {
 }
 
 impl AudioRecorder {
-    pub fn new(out: &str, sample_rate: u32, channels: u32) -> Self {
+    pub fn new(out: &str, sample_rate: u32, channels: u32) -> Result<Self, String> {
         let mut ffmpeg_cmd = std::process::Command::new("ffmpeg")
             .args([
                 "-hide_banner",
@@ -228,7 +239,7 @@ impl AudioRecorder {
             .stdin(std::process::Stdio::piped())
             .stdout(std::process::Stdio::piped())
             .spawn()
-            .expect("FFMpeg failed to start");
+            .map_err(|e| format!("FFMpeg failed to start: {}", e))?;
         let (tx, rx) = std::sync::mpsc::channel();
         let output_lines = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
         let ol_cloned = std::sync::Arc::clone(&output_lines);
@@ -243,21 +254,22 @@ impl AudioRecorder {
                     .expect("Failed to send FFMpeg line to main thread")
             }
         });
-        Self {
+        Ok(Self {
             ffmpeg: ffmpeg_cmd,
             status_receiver: rx,
             audio_length: 0,
-        }
+        })
     }
 
-    pub fn process_audio(&mut self, audio: Vec<u8>) {
+    pub fn process_audio(&mut self, audio: Vec<u8>) -> Result<(), String> {
         self.ffmpeg
             .stdin
             .as_mut()
-            .unwrap()
+            .ok_or("FFMpeg's input is already closed")?
             .write_all(audio.as_slice())
-            .expect("Pipe is closed.");
+            .map_err(|e| format!("FFMpeg stopped taking audio: {}", e))?;
         self.audio_length += 1;
+        Ok(())
     }
 
     pub fn get_render_status(&mut self) -> Option<FFMpegStatus> {
@@ -326,4 +338,46 @@ impl AudioRecorder {
     pub fn kill(&mut self) {
         self.ffmpeg.kill().unwrap();
     }
-}
\ No newline at end of file
+}
+
+impl Drop for AudioRecorder {
+    /// An unfinished recording (one that never got to [`AudioRecorder::done`]) isn't left running in the background
+    fn drop(&mut self) {
+        if self.ffmpeg.stdin.is_some() {
+            let _ = self.ffmpeg.kill();
+            let _ = self.ffmpeg.wait();
+        }
+    }
+}
+
+/// Muxes a rendered video and audio file into `out` without re-encoding either stream
+pub fn mux(video: &str, audio: &str, out: &str) -> Result<(), String> {
+    let status = std::process::Command::new("ffmpeg")
+        .args([
+            "-hide_banner",
+            "-loglevel",
+            "error",
+            "-i",
+            video,
+            "-i",
+            audio,
+            "-map",
+            "0:v:0",
+            "-map",
+            "1:a:0",
+            "-c",
+            "copy",
+            "-shortest",
+            "-y",
+            out,
+        ])
+        .status()
+        .map_err(|e| format!("FFMpeg failed to start: {}", e))?;
+    if !status.success() {
+        return Err(format!(
+            "FFMpeg failed to mux {} and {} ({})",
+            video, audio, status
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,147 @@
+/// Sample file formats, WAV is read directly and anything compressed gets decoded by SDL_mixer
+use std::{fmt, fs::File, io::Read, path::Path};
+
+use sdl2::{
+    mixer::{self, InitFlag, LoaderRWops, AUDIO_F32LSB, AUDIO_S16LSB},
+    rwops::RWops,
+    sys::{SDL_InitSubSystem, SDL_QuitSubSystem, SDL_INIT_AUDIO},
+};
+
+use crate::sequencer::SAMPLE_RATE;
+use crate::wav::{self, Pcm};
+
+/// Enough of the file to tell the formats apart
+const MAGIC_LENGTH: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Wav,
+    Ogg,
+    Flac,
+    Mp3,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Format::Wav => "WAV",
+            Format::Ogg => "OGG",
+            Format::Flac => "FLAC",
+            Format::Mp3 => "MP3",
+        };
+        f.write_str(name)
+    }
+}
+
+impl Format {
+    /// Goes by the magic bytes at the start of the file, the extension doesn't matter
+    pub fn detect(bytes: &[u8]) -> Result<Self, String> {
+        match bytes {
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => Ok(Format::Wav),
+            [b'O', b'g', b'g', b'S', ..] => Ok(Format::Ogg),
+            [b'f', b'L', b'a', b'C', ..] => Ok(Format::Flac),
+            // either an ID3 tag or straight into an MPEG frame sync
+            [b'I', b'D', b'3', ..] => Ok(Format::Mp3),
+            [0xff, second, ..] if second & 0xe0 == 0xe0 => Ok(Format::Mp3),
+            _ => Err("unsupported audio format, expected WAV, OGG, FLAC or MP3".to_string()),
+        }
+    }
+
+    fn init_flag(self) -> InitFlag {
+        match self {
+            Format::Wav => InitFlag::empty(),
+            Format::Ogg => InitFlag::OGG,
+            Format::Flac => InitFlag::FLAC,
+            Format::Mp3 => InitFlag::MP3,
+        }
+    }
+}
+
+/// Checks that `path` exists and looks like a format we can play, without decoding it
+pub fn check(path: &Path) -> Result<Format, String> {
+    let mut magic = Vec::with_capacity(MAGIC_LENGTH);
+    File::open(path)
+        .and_then(|file| file.take(MAGIC_LENGTH as u64).read_to_end(&mut magic))
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    Format::detect(&magic).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Decodes a sample file of any supported format
+pub fn decode(bytes: &[u8]) -> Result<Pcm, String> {
+    match Format::detect(bytes)? {
+        Format::Wav => wav::decode(bytes),
+        format => {
+            // the players already have the mixer open, the offline renderers need their own
+            let _headless = match mixer::query_spec() {
+                Ok(_) => None,
+                Err(_) => Some(Headless::open(format)?),
+            };
+            decode_with_mixer(bytes).map_err(|e| format!("failed to decode {}: {}", format, e))
+        }
+    }
+}
+
+/// Has SDL_mixer load the file and reads back what it converted it to, downmixed to mono
+fn decode_with_mixer(bytes: &[u8]) -> Result<Pcm, String> {
+    let chunk = RWops::from_bytes(bytes)?.load_wav()?;
+    let (frequency, format, channels) = mixer::query_spec()?;
+    // the chunk is already converted to the open device's format
+    let data = unsafe { std::slice::from_raw_parts((*chunk.raw).abuf, (*chunk.raw).alen as usize) };
+    let interleaved: Vec<f32> = match format {
+        AUDIO_F32LSB => data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+        AUDIO_S16LSB => data
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+            .collect(),
+        _ => return Err(format!("mixer opened with unexpected format {:#x}", format)),
+    };
+    let channels = channels.max(1) as usize;
+    if frequency <= 0 {
+        return Err(format!("mixer opened at {} Hz", frequency));
+    }
+    Ok(Pcm {
+        sample_rate: frequency as u32,
+        samples: interleaved
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect(),
+    })
+}
+
+/// SDL_mixer opened on the dummy audio driver, so the offline renderers can decode without a sound card
+struct Headless {
+    _context: mixer::Sdl2MixerContext,
+}
+
+impl Headless {
+    fn open(format: Format) -> Result<Self, String> {
+        sdl2::hint::set("SDL_AUDIODRIVER", "dummy");
+        let initialized = unsafe { SDL_InitSubSystem(SDL_INIT_AUDIO) } == 0;
+        sdl2::hint::set("SDL_AUDIODRIVER", "");
+        if !initialized {
+            return Err(sdl2::get_error());
+        }
+        let context = match mixer::init(format.init_flag()) {
+            Ok(context) => context,
+            Err(e) => {
+                unsafe { SDL_QuitSubSystem(SDL_INIT_AUDIO) };
+                return Err(format!("SDL_mixer has no {} decoder: {}", format, e));
+            }
+        };
+        if let Err(e) = mixer::open_audio(SAMPLE_RATE as i32, mixer::AUDIO_F32, 1, 4096) {
+            unsafe { SDL_QuitSubSystem(SDL_INIT_AUDIO) };
+            return Err(e);
+        }
+        Ok(Self { _context: context })
+    }
+}
+
+impl Drop for Headless {
+    fn drop(&mut self) {
+        mixer::close_audio();
+        unsafe { SDL_QuitSubSystem(SDL_INIT_AUDIO) };
+    }
+}
@@ -0,0 +1,131 @@
+/// On-screen HUD, the counters both players used to print to stdout plus where every planet is in its cycle
+use sdl2::pixels::Color;
+use sdl2::render::{Canvas, RenderTarget};
+
+use crate::alignment::{self, format_duration};
+use crate::mixer::Mixer;
+use crate::model::Planet;
+use crate::simulation;
+use crate::text;
+use crate::voices::VoiceStats;
+use crate::Text;
+
+/// Distance from the window's top left corner
+const MARGIN: i32 = 10;
+const HEADER_COLOR: Color = Color::RGB(200, 200, 200);
+
+/// What the player is doing right now
+#[derive(Debug, Clone, Copy)]
+pub struct Status {
+    /// seconds into the piece
+    pub elapsed: f64,
+    /// when the planets align again, if they do
+    pub end: Option<f64>,
+    pub speed: f64,
+    pub paused: bool,
+    /// notes ringing
+    pub voices: usize,
+    pub stats: VoiceStats,
+}
+
+/// Revolutions `planet` has completed `elapsed` seconds in, and piece seconds until its next note
+pub fn cycle(planet: &Planet, elapsed: f64) -> (u64, f64) {
+    let period = planet.period();
+    (
+        simulation::revolutions(period, elapsed),
+        simulation::until_next(period, elapsed),
+    )
+}
+
+/// Seconds with a decimal while it's short, the usual clock once it isn't
+fn countdown(seconds: f64) -> String {
+    if seconds < 60.0 {
+        format!("{:.1}s", seconds)
+    } else {
+        format_duration(seconds)
+    }
+}
+
+impl Status {
+    /// The lines above the planet table
+    fn header(&self, planets: &[Planet], mixer: &Mixer) -> Vec<String> {
+        let remaining = match self.end {
+            Some(end) => format_duration((end - self.elapsed).max(0.0)),
+            None => "forever".to_string(),
+        };
+        let alignment = match self.end {
+            Some(end) => format!("planets align at {}", format_duration(end)),
+            None => format!(
+                "no alignment within {}",
+                format_duration(alignment::horizon(planets))
+            ),
+        };
+        vec![
+            format!(
+                "{} elapsed, {} left",
+                format_duration(self.elapsed),
+                remaining
+            ),
+            format!(
+                "{}x{}, volume {:.0}%",
+                self.speed,
+                if self.paused { " (paused)" } else { "" },
+                mixer.master() * 100.0
+            ),
+            format!(
+                "{} voices (peak {}, {} stolen, {} dropped)",
+                self.voices, self.stats.peak, self.stats.stolen, self.stats.dropped
+            ),
+            alignment,
+        ]
+    }
+
+    /// One line per planet: revolutions so far, real time until its next note, its strip's gain and pan
+    /// and whether it's muted or soloed, with a `>` in front of the one the gain and pan keys change
+    fn planet_line(&self, planet: &Planet, index: usize, mixer: &Mixer) -> String {
+        let (revolutions, next) = cycle(planet, self.elapsed);
+        // while paused the countdown stands still, so show it in piece time
+        let next = if self.paused { next } else { next / self.speed };
+        let strip = mixer.strips().get(index).copied().unwrap_or_default();
+        let flag = if mixer.solo() == Some(index) {
+            "solo"
+        } else if strip.muted {
+            "mute"
+        } else {
+            ""
+        };
+        format!(
+            "{}{:<10} {:>6} rev  next {:>9}  gain {:.1} pan {:+.1} {}",
+            if mixer.selected() == index { '>' } else { ' ' },
+            planet.name,
+            revolutions,
+            countdown(next),
+            strip.gain,
+            strip.pan,
+            flag
+        )
+    }
+}
+
+/// Draws the HUD in the top left corner
+pub fn draw_hud<T: RenderTarget, C>(
+    canvas: &mut Canvas<T>,
+    (creator, font): Text<C>,
+    planets: &[Planet],
+    status: &Status,
+    mixer: &Mixer,
+) -> Result<(), String> {
+    let height = font.recommended_line_spacing();
+    let mut y = MARGIN;
+    for line in status.header(planets, mixer) {
+        text::draw_text(canvas, creator, font, &line, (MARGIN, y), HEADER_COLOR)?;
+        y += height;
+    }
+    y += height / 2;
+    for (i, planet) in planets.iter().enumerate() {
+        let line = status.planet_line(planet, i, mixer);
+        text::draw_text(canvas, creator, font, &line, (MARGIN, y), planet.color)?;
+        y += height;
+    }
+    Ok(())
+}
@@ -0,0 +1,59 @@
+/// Where the scene goes, worked out from the window's size so it fills whatever the window is
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use crate::text;
+
+/// Pixels an orbit radius of 1.0 spans in the original 800×600 window, back when orbits were given in pixels
+pub const ORIGINAL_SCALE: f32 = 300.0;
+
+/// An orbit radius given in pixels of the original window, in normalized units
+pub fn from_pixels(pixels: f32) -> f32 {
+    pixels / ORIGINAL_SCALE
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub center: (i32, i32),
+    /// pixels per unit of orbit radius, 1.0 reaches the edge of the window's shorter side
+    pub scale: f32,
+    /// drawing pixels per window point, more than 1 on HiDPI screens
+    pub dpi: f32,
+}
+
+impl Layout {
+    /// The scene centered in `(width, height)` drawing pixels
+    pub fn new((width, height): (u32, u32), dpi: f32) -> Self {
+        Self {
+            center: (width as i32 / 2, height as i32 / 2),
+            scale: width.min(height) as f32 / 2.0,
+            dpi,
+        }
+    }
+
+    /// Fills the window, going by its drawable size which on HiDPI screens is bigger than its size in points
+    pub fn for_window(canvas: &Canvas<Window>) -> Self {
+        let points = canvas.window().size();
+        let pixels = canvas.output_size().unwrap_or(points);
+        Self::new(pixels, pixels.0 as f32 / points.0.max(1) as f32)
+    }
+
+    /// Pixel position on an orbit of normalized `radius` at `angle`
+    pub fn point(&self, radius: f32, angle: f32) -> (f32, f32) {
+        let radius = radius * self.scale;
+        (
+            self.center.0 as f32 + radius * angle.cos(),
+            self.center.1 as f32 + radius * angle.sin(),
+        )
+    }
+
+    /// `size` points in drawing pixels, for things that shouldn't shrink on HiDPI screens
+    pub fn pixels(&self, size: f32) -> f32 {
+        size * self.dpi
+    }
+
+    /// [`text::FONT_SIZE`] scaled for the screen
+    pub fn font_size(&self) -> u16 {
+        self.pixels(text::FONT_SIZE as f32).round() as u16
+    }
+}
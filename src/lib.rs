@@ -1,194 +1,363 @@
-use sdl2::mixer::LoaderRWops;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::render::{Canvas, RenderTarget, TextureCreator};
+use sdl2::ttf::Font;
+use sdl2::video::FullscreenType;
 use sdl2::{
     self,
-    mixer::{Channel, AUDIO_F32, DEFAULT_CHANNELS},
+    mixer::{AUDIO_F32, DEFAULT_CHANNELS},
     sys::SDL_Delay,
 };
 use std::time::{Duration, Instant};
-use std::{cell::RefCell, collections::HashMap, io::Write, process::exit, rc::Rc};
+use std::{cell::RefCell, process::exit, rc::Rc};
 
+pub mod alignment;
+pub mod audio;
+pub mod cli;
+pub mod clock;
+pub mod controls;
 pub mod emscripten;
+pub mod events;
+pub mod ffmpeg;
+pub mod formats;
+pub mod hud;
+pub mod layout;
+pub mod live_midi;
+pub mod midi;
+pub mod mixer;
+pub mod model;
 pub mod multi_threaded;
+pub mod offline;
+pub mod osc;
+pub mod pattern;
+pub mod render;
+pub mod sequencer;
+pub mod simulation;
+pub mod synth;
+pub mod system;
+pub mod tempo;
+pub mod text;
+pub mod tuning;
+pub mod voices;
+pub mod wav;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Note {
-    Mercury,
-    Venus,
-    Earth,
-    Mars,
-    Jupiter,
-    Saturn,
-    Uranus,
-    Neptune,
-    Pluto,
+use controls::{Action, View};
+use layout::Layout;
+pub use model::rotation_around_sun_days;
+use model::Planet;
+use pattern::PatternLayer;
+
+/// Where a planet sits on its orbit `elapsed` seconds into the piece
+fn orbit_position(planet: &Planet, layout: &Layout, elapsed: f64) -> (f32, f32) {
+    let angle = simulation::angle(planet.period(), elapsed) as f32;
+    layout.point(planet.orbit_radius, angle)
+}
+
+/// How long the window stays open once the planets have aligned, for the last notes to ring out
+pub(crate) const LINGER: Duration = Duration::from_secs(20);
+
+/// How long the line from the center stays up after a note fires, in seconds
+const FLASH_DURATION: f64 = 0.2;
+
+/// Whether the planet's note fired within the last [`FLASH_DURATION`]
+fn flashing(planet: &Planet, elapsed: f64) -> bool {
+    simulation::since_last(planet.period(), elapsed) < FLASH_DURATION
+}
+
+/// Side of a planet's square when they're not drawn to scale, sizes here are in points
+const PLANET_SIZE: f32 = 10.0;
+/// The biggest planet's size when they are, and the least any planet gets so it doesn't vanish
+const MAX_PLANET_SIZE: f32 = 24.0;
+const MIN_PLANET_SIZE: f32 = 3.0;
+const SUN_RADIUS: f32 = 12.0;
+/// How far back along its orbit a trail reaches, in radians, and how many segments it fades over
+const TRAIL_ANGLE: f32 = std::f32::consts::FRAC_PI_2;
+const TRAIL_SEGMENTS: usize = 24;
+
+/// Draws the planet as a `size` pixel square, plus the white line from the center while its note is flashing
+fn draw_planet<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    center: (i32, i32),
+    color: sdl2::pixels::Color,
+    (x, y): (f32, f32),
+    size: u32,
+    flash: bool,
+) {
+    canvas.set_draw_color(color);
+    let half = size as i32 / 2;
+    let _ = canvas.fill_rect(sdl2::rect::Rect::new(
+        x as i32 - half,
+        y as i32 - half,
+        size,
+        size,
+    ));
+    if flash {
+        canvas.set_draw_color(sdl2::pixels::Color::RGB(255, 255, 255));
+        let _ = canvas.draw_line(
+            sdl2::rect::Point::new(center.0, center.1),
+            sdl2::rect::Point::new(x as i32, y as i32),
+        );
+    }
 }
 
-impl Note {
-    fn to_bytes(&self) -> &'static [u8] {
-        match self {
-            Note::Mercury => rotation_around_sun_days::MERCURY_NOTE,
-            Note::Venus => rotation_around_sun_days::VENUS_NOTE,
-            Note::Earth => rotation_around_sun_days::EARTH_NOTE,
-            Note::Mars => rotation_around_sun_days::MARS_NOTE,
-            Note::Jupiter => rotation_around_sun_days::JUPITER_NOTE,
-            Note::Saturn => rotation_around_sun_days::SATURN_NOTE,
-            Note::Uranus => rotation_around_sun_days::URANUS_NOTE,
-            Note::Neptune => rotation_around_sun_days::NEPTUNE_NOTE,
-            Note::Pluto => rotation_around_sun_days::PLUTO_NOTE,
+/// Draws the planet's orbit as a thin grey ring
+fn draw_orbit<T: RenderTarget>(canvas: &mut Canvas<T>, layout: &Layout, radius: f32) {
+    const SEGMENTS: usize = 120;
+    let points: Vec<sdl2::rect::Point> = (0..=SEGMENTS)
+        .map(|i| {
+            let angle = i as f32 / SEGMENTS as f32 * 2.0 * std::f32::consts::PI;
+            let (x, y) = layout.point(radius, angle);
+            sdl2::rect::Point::new(x.round() as i32, y.round() as i32)
+        })
+        .collect();
+    canvas.set_draw_color(sdl2::pixels::Color::RGB(60, 60, 60));
+    let _ = canvas.draw_lines(points.as_slice());
+}
+
+/// `planet`'s square drawn to scale against `largest`, the biggest diameter around.
+/// Planets without a diameter keep the usual size.
+fn planet_size(planet: &Planet, largest: f32) -> f32 {
+    match planet.diameter {
+        Some(diameter) if largest > 0.0 => {
+            (MAX_PLANET_SIZE * diameter / largest).max(MIN_PLANET_SIZE)
         }
+        _ => PLANET_SIZE,
     }
 }
 
-pub mod rotation_around_sun_days {
-    pub const MERCURY_FACTOR: f32 = 88.0;
-    pub const VENUS_FACTOR: f32 = 224.7;
-    pub const EARTH_FACTOR: f32 = 365.256;
-    pub const MARS_FACTOR: f32 = 686.93;
-    pub const JUPITER_FACTOR: f32 = 4_333.0;
-    pub const SATURN_FACTOR: f32 = 10_756.0;
-    pub const URANUS_FACTOR: f32 = 30_687.0;
-    pub const NEPTUNE_FACTOR: f32 = 60_190.0;
-    pub const PLUTO_FACTOR: f32 = (248.0 * EARTH_FACTOR) as f32;
+/// Draws the orbit behind the planet in its color, fading out towards the tail
+fn draw_trail<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    planet: &Planet,
+    layout: &Layout,
+    elapsed: f64,
+) {
+    let angle = simulation::angle(planet.period(), elapsed) as f32;
+    let point = |angle: f32| {
+        let (x, y) = layout.point(planet.orbit_radius, angle);
+        sdl2::rect::Point::new(x.round() as i32, y.round() as i32)
+    };
+    for i in 0..TRAIL_SEGMENTS {
+        let from = angle - TRAIL_ANGLE * i as f32 / TRAIL_SEGMENTS as f32;
+        let to = angle - TRAIL_ANGLE * (i + 1) as f32 / TRAIL_SEGMENTS as f32;
+        // faded towards the black background rather than blended, so it looks the same on every canvas
+        let fade = 1.0 - i as f32 / TRAIL_SEGMENTS as f32;
+        let (r, g, b) = planet.color.rgb();
+        canvas.set_draw_color(sdl2::pixels::Color::RGB(
+            (r as f32 * fade) as u8,
+            (g as f32 * fade) as u8,
+            (b as f32 * fade) as u8,
+        ));
+        let _ = canvas.draw_line(point(from), point(to));
+    }
+}
 
-    pub const MERCURY: f32 = MERCURY_FACTOR / MERCURY_FACTOR;
-    pub const VENUS: f32 = VENUS_FACTOR / MERCURY_FACTOR;
-    pub const EARTH: f32 = EARTH_FACTOR / MERCURY_FACTOR;
-    pub const MARS: f32 = MARS_FACTOR / MERCURY_FACTOR;
-    pub const JUPITER: f32 = JUPITER_FACTOR / MERCURY_FACTOR;
-    pub const SATURN: f32 = SATURN_FACTOR / MERCURY_FACTOR;
-    pub const URANUS: f32 = URANUS_FACTOR / MERCURY_FACTOR;
-    pub const NEPTUNE: f32 = NEPTUNE_FACTOR / MERCURY_FACTOR;
-    pub const PLUTO: f32 = PLUTO_FACTOR / MERCURY_FACTOR;
+/// Draws a filled yellow disc in the middle
+fn draw_sun<T: RenderTarget>(canvas: &mut Canvas<T>, layout: &Layout) {
+    let center = layout.center;
+    let radius = layout.pixels(SUN_RADIUS).round() as i32;
+    canvas.set_draw_color(sdl2::pixels::Color::RGB(255, 210, 60));
+    for dy in -radius..=radius {
+        let dx = ((radius * radius - dy * dy) as f32).sqrt().round() as i32;
+        let _ = canvas.draw_line(
+            sdl2::rect::Point::new(center.0 - dx, center.1 + dy),
+            sdl2::rect::Point::new(center.0 + dx, center.1 + dy),
+        );
+    }
+}
 
-    pub const MERCURY_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_1_61Hz.wav");
-    pub const VENUS_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_2_123Hz.wav");
-    pub const EARTH_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_3_185Hz.wav");
-    pub const MARS_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_4_246Hz.wav");
-    pub const JUPITER_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_5_311Hz.wav");
-    pub const SATURN_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_6_370Hz.wav");
-    pub const URANUS_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_7_440Hz.wav");
-    pub const NEPTUNE_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_8_493Hz.wav");
-    pub const PLUTO_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_9_554Hz.wav");
+/// Font and texture creator for drawing labels
+type Text<'a, C> = (&'a TextureCreator<C>, &'a Font<'a, 'static>);
 
-    pub const MERCURY_ORIGINAL_BPM: f32 = 124.56;
-    pub const VENUS_ORIGINAL_BPM: f32 = 48.766;
-    pub const EARTH_ORIGINAL_BPM: f32 = 30.0;
-    pub const MARS_ORIGINAL_BPM: f32 = 15.9505;
-    pub const JUPITER_ORIGINAL_BPM: f32 = 2.52913;
-    pub const SATURN_ORIGINAL_BPM: f32 = 1.01845;
-    pub const URANUS_ORIGINAL_BPM: f32 = 0.3571;
-    pub const NEPTUNE_ORIGINAL_BPM: f32 = 0.1821;
-    pub const PLUTO_ORIGINAL_BPM: f32 = 0.12113;
+/// Draws every planet, with its orbit, trail, name and the sun if `view` asks for them; `flash` says whether planet `i` is flashing
+fn draw_scene<T: RenderTarget, C>(
+    canvas: &mut Canvas<T>,
+    text: Text<C>,
+    planets: &[Planet],
+    layout: &Layout,
+    elapsed: f64,
+    view: View,
+    flash: impl Fn(usize) -> bool,
+) {
+    if view.orbits {
+        for planet in planets {
+            draw_orbit(canvas, layout, planet.orbit_radius);
+        }
+    }
+    if view.trails {
+        for planet in planets {
+            draw_trail(canvas, planet, layout, elapsed);
+        }
+    }
+    if view.sun {
+        draw_sun(canvas, layout);
+    }
+    let largest = planets
+        .iter()
+        .filter_map(|planet| planet.diameter)
+        .fold(0.0, f32::max);
+    for (i, planet) in planets.iter().enumerate() {
+        let position = orbit_position(planet, layout, elapsed);
+        let size = if view.sizes {
+            planet_size(planet, largest)
+        } else {
+            PLANET_SIZE
+        };
+        let size = layout.pixels(size).round() as u32;
+        draw_planet(
+            canvas,
+            layout.center,
+            planet.color,
+            position,
+            size,
+            flash(i),
+        );
+        if view.labels {
+            let (creator, font) = text;
+            let at = (
+                position.0 as i32 + size as i32 / 2 + layout.pixels(3.0) as i32,
+                position.1 as i32 - layout.pixels(8.0) as i32,
+            );
+            let _ = text::draw_text(canvas, creator, font, &planet.name, at, planet.color);
+        }
+    }
 }
 
-struct Planet {
-    note: Note,
-    bpm: f32,
-    orbit_radius: i32,
-    color: sdl2::pixels::Color,
-    next_fire: Instant,
+/// Switches between a window and fullscreen on whatever display it's on
+fn toggle_fullscreen(canvas: &mut Canvas<sdl2::video::Window>) -> Result<(), String> {
+    let window = canvas.window_mut();
+    let next = match window.fullscreen_state() {
+        FullscreenType::Off => FullscreenType::Desktop,
+        _ => FullscreenType::Off,
+    };
+    window.set_fullscreen(next)
 }
 
-/// you can't spawn threads in wasm, oh well :(
+/// Applies a keyboard action to the single threaded player
+fn apply(playback: &mut audio::Playback, action: Action) {
+    let clock = *playback.sequencer.clock();
+    match action {
+        Action::TogglePause => playback.set_paused(!clock.paused()),
+        Action::Seek(seconds) => playback.seek(playback.elapsed() + seconds),
+        Action::Restart => playback.seek(0.0),
+        Action::Speed(factor) => playback.set_speed(clock.speed() * factor),
+        Action::Volume(step) => {
+            let mixer = playback.sequencer.mixer_mut();
+            mixer.set_master(mixer.master() + step);
+        }
+        Action::Mute(planet) => playback.sequencer.mixer_mut().toggle_mute(planet),
+        Action::Solo(planet) => playback.sequencer.mixer_mut().toggle_solo(planet),
+        Action::Select(step) => playback.sequencer.mixer_mut().select(step),
+        Action::Gain(step) => playback.sequencer.mixer_mut().nudge_gain(step),
+        Action::Pan(step) => playback.sequencer.mixer_mut().nudge_pan(step),
+        Action::Spatial => {
+            let mixer = playback.sequencer.mixer_mut();
+            mixer.set_spatial(mixer.spatial().next());
+        }
+        Action::ToggleOrbits
+        | Action::ToggleLabels
+        | Action::ToggleTrails
+        | Action::ToggleSun
+        | Action::ToggleSizes
+        | Action::TogglePattern
+        | Action::ToggleHud
+        | Action::ToggleFullscreen => {}
+    }
+}
 
+/// you can't spawn threads in wasm, oh well :(
 pub fn main_loop(
     sdl_context: Rc<RefCell<sdl2::Sdl>>,
     canvas: Rc<RefCell<sdl2::render::Canvas<sdl2::video::Window>>>,
+    planets: &[Planet],
+    settings: cli::Settings,
+    mut outputs: events::Outputs,
 ) -> Result<(), String> {
+    let cli::Settings {
+        clock: start,
+        spatial,
+        polyphony,
+        mut view,
+        pattern,
+    } = settings;
     better_panic::Settings::new()
         .lineno_suffix(true)
         .verbosity(better_panic::Verbosity::Full)
         .install();
     let mut event_pump = sdl_context.borrow_mut().event_pump().unwrap();
+    let ttf = sdl2::ttf::init().map_err(|e| e.to_string())?;
+    let mut layout = Layout::for_window(&canvas.borrow());
+    let mut font = text::load_font(&ttf, layout.font_size())?;
+    let texture_creator = canvas.borrow().texture_creator();
+    let size = canvas.borrow().output_size()?;
+    let mut pattern = pattern
+        .map(|pattern| PatternLayer::new(pattern, &texture_creator, size))
+        .transpose()?;
 
-    let _mixer = sdl2::mixer::init(sdl2::mixer::InitFlag::all()).unwrap();
-    sdl2::mixer::open_audio(44100, AUDIO_F32, DEFAULT_CHANNELS, 2048).unwrap();
-    sdl2::mixer::allocate_channels(100);
-    sdl2::mixer::Channel::all().set_volume(100);
-
-    let now = Instant::now();
-
-    let mut planets = vec![
-        Planet {
-            note: Note::Mercury,
-            bpm: rotation_around_sun_days::MERCURY_ORIGINAL_BPM,
-            orbit_radius: 50,
-            color: sdl2::pixels::Color::GREY,
-            next_fire: now,
-        },
-        Planet {
-            note: Note::Venus,
-            bpm: rotation_around_sun_days::VENUS_ORIGINAL_BPM,
-            orbit_radius: 75,
-            color: sdl2::pixels::Color::RGB(255, 165, 0),
-            next_fire: now,
-        },
-        Planet {
-            note: Note::Earth,
-            bpm: rotation_around_sun_days::EARTH_ORIGINAL_BPM,
-            orbit_radius: 100,
-            color: sdl2::pixels::Color::RGB(0, 0, 255),
-            next_fire: now,
-        },
-        Planet {
-            note: Note::Mars,
-            bpm: rotation_around_sun_days::MARS_ORIGINAL_BPM,
-            orbit_radius: 125,
-            color: sdl2::pixels::Color::RGB(255, 100, 0),
-            next_fire: now,
-        },
-        Planet {
-            note: Note::Jupiter,
-            bpm: rotation_around_sun_days::JUPITER_ORIGINAL_BPM,
-            orbit_radius: 150,
-            color: sdl2::pixels::Color::RGB(218, 165, 200),
-            next_fire: now,
-        },
-        Planet {
-            note: Note::Saturn,
-            bpm: rotation_around_sun_days::SATURN_ORIGINAL_BPM,
-            orbit_radius: 175,
-            color: sdl2::pixels::Color::RGB(210, 180, 140),
-            next_fire: now,
-        },
-        Planet {
-            note: Note::Uranus,
-            bpm: rotation_around_sun_days::URANUS_ORIGINAL_BPM,
-            orbit_radius: 200,
-            color: sdl2::pixels::Color::RGB(0, 255, 255),
-            next_fire: now,
-        },
-        Planet {
-            note: Note::Neptune,
-            bpm: rotation_around_sun_days::NEPTUNE_ORIGINAL_BPM,
-            orbit_radius: 225,
-            color: sdl2::pixels::Color::RGB(0, 0, 139),
-            next_fire: now,
-        },
-        Planet {
-            note: Note::Pluto,
-            bpm: rotation_around_sun_days::PLUTO_ORIGINAL_BPM,
-            orbit_radius: 250,
-            color: sdl2::pixels::Color::RGB(128, 128, 128),
-            next_fire: now,
-        },
-    ];
+    let _mixer = sdl2::mixer::init(sdl2::mixer::InitFlag::all())?;
+    sdl2::mixer::open_audio(
+        sequencer::SAMPLE_RATE as i32,
+        AUDIO_F32,
+        DEFAULT_CHANNELS,
+        2048,
+    )?;
 
-    let start_time = Instant::now();
-    let mut first_time = false;
-    let mut first_time_instant = Instant::now();
-    let flash_duration = Duration::from_millis(200);
-
-    // Make flashery persist across frames
-    let mut flashery: HashMap<Note, Instant> = HashMap::new();
-    let mut preloaded_chunks = HashMap::new();
-    let mut max_channels_used = 0;
+    let end = alignment::predicted_duration(planets);
+    let mut sequencer = offline::planet_sequencer(planets, spatial, polyphony)?;
+    sequencer.set_end(end.map(alignment::last_note));
+    if outputs.listening() {
+        sequencer.log_events();
+    }
+    let playback = audio::install(sequencer)?;
+    {
+        let mut playback = playback.lock().unwrap();
+        playback.audio = outputs.audio;
+        playback.set_speed(start.speed());
+        playback.seek(start.seconds());
+    }
 
+    match end {
+        Some(end) => println!("Planets align again in {}", alignment::format_duration(end)),
+        None => println!(
+            "Planets don't align again within {}, playing forever",
+            alignment::format_duration(alignment::horizon(planets))
+        ),
+    }
+    // when the planets were found aligned, the window closes LINGER after that
+    let mut completed: Option<Instant> = None;
     'running: loop {
         // Process events
         for event in event_pump.poll_iter() {
-            if let sdl2::event::Event::Quit { .. } = event {
-                break 'running;
+            match event {
+                Event::Quit { .. } => break 'running,
+                Event::KeyDown {
+                    keycode: Some(key),
+                    keymod,
+                    ..
+                } => match controls::action(key, keymod).and_then(|action| view.apply(action)) {
+                    Some(Action::ToggleFullscreen) => toggle_fullscreen(&mut canvas.borrow_mut())?,
+                    Some(action) => {
+                        let mut playback = playback.lock().unwrap();
+                        apply(&mut playback, action);
+                        if action == Action::TogglePause && playback.sequencer.clock().paused() {
+                            outputs.silence();
+                        }
+                    }
+                    None => {}
+                },
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(..),
+                    ..
+                } => {
+                    let resized = Layout::for_window(&canvas.borrow());
+                    if resized.dpi != layout.dpi {
+                        font = text::load_font(&ttf, resized.font_size())?;
+                    }
+                    if let Some(pattern) = &mut pattern {
+                        pattern.resize(&texture_creator, canvas.borrow().output_size()?)?;
+                    }
+                    layout = resized;
+                }
+                _ => {}
             }
         }
 
@@ -197,79 +366,81 @@ pub fn main_loop(
             .set_draw_color(sdl2::pixels::Color::RGB(0, 0, 0));
         canvas.borrow_mut().clear();
 
-        let center_x = 400;
-        let center_y = 300;
-        let current_time = Instant::now();
-        let elapsed = current_time.duration_since(start_time).as_secs_f32();
-
-        let mut flash_count = 0;
-
-        for planet in &mut planets {
-            let period = 60.0 / planet.bpm;
-            let angle = (elapsed / period) * 2.0 * std::f32::consts::PI;
-            let x = center_x as f32 + planet.orbit_radius as f32 * angle.cos();
-            let y = center_y as f32 + planet.orbit_radius as f32 * angle.sin();
-
-            // Draw the planet
-            canvas.borrow_mut().set_draw_color(planet.color);
-            let _ = canvas.borrow_mut().fill_rect(sdl2::rect::Rect::new(
-                x as i32 - 5,
-                y as i32 - 5,
-                10,
-                10,
-            ));
-
-            // Check if it's time to trigger a note
-            if current_time >= planet.next_fire {
-                planet.next_fire = current_time + Duration::from_secs_f32(period);
-                // Play the note (load if necessary)
-                let chunk = preloaded_chunks.entry(planet.note).or_insert_with(|| {
-                    sdl2::rwops::RWops::from_bytes(planet.note.to_bytes())
-                        .unwrap()
-                        .load_wav()
-                        .unwrap()
-                });
-                Channel::all().play(chunk, 0).unwrap();
-                // Record flash event time
-                flashery.insert(planet.note, current_time);
-            }
+        // notes are scheduled by the audio callback, the visuals just follow its clock
+        let (status, mixer, notes) = {
+            let mut playback = playback.lock().unwrap();
+            let clock = playback.sequencer.clock();
+            let status = hud::Status {
+                elapsed: playback.elapsed(),
+                end,
+                speed: clock.speed(),
+                paused: clock.paused(),
+                voices: playback.sequencer.voices(),
+                stats: playback.sequencer.stats(),
+            };
+            let notes = playback.sequencer.take_events();
+            (status, playback.sequencer.mixer().clone(), notes)
+        };
+        let elapsed = status.elapsed;
+        if let Some(osc) = &mut outputs.osc {
+            osc.update(elapsed);
+        }
+        // picked up once a frame, so MIDI can run up to a frame behind the samples
+        for note in &notes {
+            outputs.send(note, mixer.audible(note.planet))?;
+        }
 
-            // Check if the flash event should still be visible
-            if let Some(&fire_time) = flashery.get(&planet.note) {
-                if current_time.duration_since(fire_time) < flash_duration {
-                    canvas
-                        .borrow_mut()
-                        .set_draw_color(sdl2::pixels::Color::RGB(255, 255, 255));
-                    let _ = canvas.borrow_mut().draw_line(
-                        sdl2::rect::Point::new(center_x, center_y),
-                        sdl2::rect::Point::new(x as i32, y as i32),
-                    );
-                    flash_count += 1;
-                }
+        if let Some(pattern) = &mut pattern {
+            let mut canvas = canvas.borrow_mut();
+            pattern.update(&mut canvas, planets, &layout, elapsed)?;
+            if view.pattern {
+                pattern.draw(&mut canvas)?;
             }
         }
+        draw_scene(
+            &mut canvas.borrow_mut(),
+            (&texture_creator, &font),
+            planets,
+            &layout,
+            elapsed,
+            view,
+            |i| flashing(&planets[i], elapsed),
+        );
+        if view.hud {
+            hud::draw_hud(
+                &mut canvas.borrow_mut(),
+                (&texture_creator, &font),
+                planets,
+                &status,
+                &mixer,
+            )?;
+        }
 
-        if flash_count == 9 && !first_time {
-            // all planets flashed simultaneously for the first time
-            first_time = true;
-            first_time_instant = Instant::now();
-        } else if flash_count == 9 && first_time && first_time_instant.elapsed().as_secs() > 3 {
-            println!("Completed");
-            std::thread::sleep(Duration::from_secs(20));
-            break 'running;
+        // the sequencer has stopped triggering by now, the loop keeps going so the window stays responsive
+        if end.is_some_and(|end| elapsed >= end) {
+            let since = *completed.get_or_insert_with(|| {
+                println!("Completed");
+                Instant::now()
+            });
+            if since.elapsed() >= LINGER {
+                break 'running;
+            }
+        } else {
+            // seeked back before the end
+            completed = None;
         }
 
         canvas.borrow_mut().present();
         unsafe {
             SDL_Delay(Duration::from_secs_f32(1.0 / 60.0).as_millis() as u32);
         }
-        max_channels_used = max_channels_used.max(sdl2::mixer::get_playing_channels_number());
-        print!(
-            "\rMax channels used: {} Playing channels: {}",
-            max_channels_used,
-            sdl2::mixer::get_playing_channels_number()
-        );
-        std::io::stdout().flush().unwrap();
     }
+    let notes = playback.lock().unwrap().sequencer.take_events();
+    for note in &notes {
+        // too late to play these, but they belong in the log
+        outputs.send(note, false)?;
+    }
+    outputs.close()?;
+    audio::uninstall();
     exit(0);
 }
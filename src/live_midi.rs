@@ -0,0 +1,199 @@
+/// Live MIDI output, the players' triggers sent to a MIDI port as they happen so the planets can drive external synths.
+/// Needs the `midi-out` feature, without it asking for a port is an error.
+use std::sync::mpsc::Sender;
+#[cfg(feature = "midi-out")]
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::thread::JoinHandle;
+#[cfg(feature = "midi-out")]
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "midi-out")]
+use midir::{MidiOutput, MidiOutputConnection, MidiOutputPort};
+
+use crate::events::NoteEvent;
+#[cfg(feature = "midi-out")]
+use crate::midi::{self, Voicing};
+use crate::model::Planet;
+
+/// What the output thread gets sent
+#[cfg_attr(not(feature = "midi-out"), allow(dead_code))]
+enum Message {
+    Note(NoteEvent),
+    /// note offs for everything sounding
+    Silence,
+}
+
+/// A MIDI output port with its own thread, which sends every planet's channel setup on connecting
+/// and then the notes as they're played, each note off once the planet's sample would have ended
+pub struct LiveMidi {
+    sender: MidiSender,
+    thread: JoinHandle<()>,
+}
+
+/// Plays notes on a [`LiveMidi`] port from another thread
+#[derive(Clone)]
+pub struct MidiSender(Sender<Message>);
+
+impl MidiSender {
+    /// Sends `note` out right away, call it the moment the note is due
+    pub fn play(&self, note: &NoteEvent) {
+        let _ = self.0.send(Message::Note(*note));
+    }
+
+    /// Ends every note still sounding, they start again with the next trigger
+    pub fn silence(&self) {
+        let _ = self.0.send(Message::Silence);
+    }
+}
+
+impl LiveMidi {
+    /// Connects to the first output port with `port` in its name, ignoring case. If there's none, a virtual port
+    /// called `port` is opened instead for other programs to connect to (not on Windows, which can't do that).
+    #[cfg(feature = "midi-out")]
+    pub fn connect(port: &str, planets: &[Planet]) -> Result<Self, String> {
+        let voicings = planets
+            .iter()
+            .enumerate()
+            .map(|(i, planet)| Voicing::new(planet, i))
+            .collect::<Result<Vec<_>, String>>()?;
+        let lengths = planets
+            .iter()
+            .map(|planet| midi::note_length(planet).map(Duration::from_secs_f64))
+            .collect::<Result<Vec<_>, String>>()?;
+        let connection = open(port)?;
+        let (sender, received) = std::sync::mpsc::channel();
+        let thread = std::thread::spawn(move || run(connection, &voicings, &lengths, received));
+        Ok(Self {
+            sender: MidiSender(sender),
+            thread,
+        })
+    }
+
+    #[cfg(not(feature = "midi-out"))]
+    pub fn connect(_port: &str, _planets: &[Planet]) -> Result<Self, String> {
+        Err("--midi-out needs mandala built with the midi-out feature".to_string())
+    }
+
+    /// See [`MidiSender::play`]
+    pub fn play(&self, note: &NoteEvent) {
+        self.sender.play(note);
+    }
+
+    /// See [`MidiSender::silence`]
+    pub fn silence(&self) {
+        self.sender.silence();
+    }
+
+    /// For playing notes from another thread than the one that owns the port
+    pub fn sender(&self) -> MidiSender {
+        self.sender.clone()
+    }
+
+    /// Ends the notes still sounding and closes the port, once every [`MidiSender`] has been dropped
+    pub fn close(self) {
+        drop(self.sender);
+        let _ = self.thread.join();
+    }
+}
+
+/// See [`LiveMidi::connect`]
+#[cfg(feature = "midi-out")]
+fn open(port: &str) -> Result<MidiOutputConnection, String> {
+    let output =
+        MidiOutput::new("mandala").map_err(|e| format!("can't open MIDI output: {}", e))?;
+    let names: Vec<(MidiOutputPort, String)> = output
+        .ports()
+        .into_iter()
+        .filter_map(|p| output.port_name(&p).ok().map(|name| (p, name)))
+        .collect();
+    let wanted = port.to_lowercase();
+    if let Some((found, name)) = names
+        .iter()
+        .find(|(_, name)| name.to_lowercase().contains(&wanted))
+    {
+        println!("Sending MIDI to {}", name);
+        return output
+            .connect(found, "mandala")
+            .map_err(|e| format!("can't connect to MIDI port {}: {}", name, e));
+    }
+    #[cfg(unix)]
+    {
+        use midir::os::unix::VirtualOutput;
+        println!("Opened virtual MIDI port {}", port);
+        output
+            .create_virtual(port)
+            .map_err(|e| format!("can't open virtual MIDI port {}: {}", port, e))
+    }
+    #[cfg(not(unix))]
+    {
+        let names: Vec<&str> = names.iter().map(|(_, name)| name.as_str()).collect();
+        Err(format!(
+            "no MIDI output port called '{}', there's {}",
+            port,
+            if names.is_empty() {
+                "none at all".to_string()
+            } else {
+                names.join(", ")
+            }
+        ))
+    }
+}
+
+/// The output thread, until every sender has hung up
+#[cfg(feature = "midi-out")]
+fn run(
+    mut connection: MidiOutputConnection,
+    voicings: &[Voicing],
+    lengths: &[Duration],
+    notes: Receiver<Message>,
+) {
+    let mut send = |message: [u8; 3]| {
+        let _ = connection.send(&message);
+    };
+    for voicing in voicings {
+        voicing.setup().into_iter().for_each(&mut send);
+    }
+    // when each planet's sounding note should end
+    let mut offs: Vec<Option<Instant>> = vec![None; voicings.len()];
+    loop {
+        let received = match offs.iter().flatten().min() {
+            Some(off) => notes.recv_timeout(off.saturating_duration_since(Instant::now())),
+            None => notes.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        let now = Instant::now();
+        for (planet, off) in offs.iter_mut().enumerate() {
+            if off.is_some_and(|off| off <= now) {
+                send(voicings[planet].note_off());
+                *off = None;
+            }
+        }
+        match received {
+            Ok(Message::Note(note)) => {
+                let voicing = &voicings[note.planet];
+                // retriggering a note that's still on would leave a synth with two of the same key down
+                if offs[note.planet].take().is_some() {
+                    send(voicing.note_off());
+                }
+                send(voicing.note_on());
+                // the sample plays at its own pitch whatever the speed, but the MIDI note has to end before
+                // the planet's next one, which comes around `speed` times sooner
+                offs[note.planet] = Some(now + lengths[note.planet].div_f64(note.speed));
+            }
+            Ok(Message::Silence) => {
+                for (planet, off) in offs.iter_mut().enumerate() {
+                    if off.take().is_some() {
+                        send(voicings[planet].note_off());
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    for (planet, off) in offs.iter().enumerate() {
+        if off.is_some() {
+            send(voicings[planet].note_off());
+        }
+    }
+    connection.close();
+}
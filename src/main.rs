@@ -1,15 +1,87 @@
 use std::{cell::RefCell, rc::Rc};
 
+/// Prints a startup error like a missing sample file and quits, rather than panicking with a backtrace
+fn or_exit<T>(result: Result<T, String>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("mandala: {}", e);
+        std::process::exit(1)
+    })
+}
+
 fn main() {
-    if std::env::args().nth(1) == Some("multi".to_string()) {
-        mandala::multi_threaded::multi_threaded_main().unwrap();
+    let args = or_exit(mandala::cli::Args::parse(std::env::args().skip(1)));
+    let planets = or_exit(args.planets());
+    let settings = or_exit(args.settings(&planets));
+    // optional duration in seconds right after the output path
+    let seconds = || match args.positional.get(1) {
+        Some(arg) => match arg.parse::<f64>() {
+            Ok(seconds) if seconds.is_finite() && seconds >= 0.0 => Ok(seconds),
+            _ => Err(format!(
+                "the duration must be a number of seconds, got '{}'",
+                arg
+            )),
+        },
+        None => Ok(mandala::offline::default_duration(&planets)),
+    };
+    // the output path every export command starts with
+    let out = |usage: &str| {
+        or_exit(
+            args.positional
+                .first()
+                .ok_or_else(|| format!("usage: {}", usage)),
+        )
+    };
+    if args.command.as_deref() == Some("multi") {
+        or_exit(mandala::multi_threaded::multi_threaded_main(
+            &planets,
+            settings,
+            or_exit(args.outputs(&planets)),
+        ));
+    } else if args.command.as_deref() == Some("wav") {
+        // mandala wav <out.wav> [seconds] [--float]
+        let out = out("mandala wav <out.wav> [seconds] [--float]");
+        let format = if args.float {
+            mandala::wav::SampleFormat::F32
+        } else {
+            mandala::wav::SampleFormat::I16
+        };
+        or_exit(mandala::offline::render_wav(
+            &planets,
+            out,
+            or_exit(seconds()),
+            format,
+            settings.spatial,
+            settings.polyphony,
+        ));
+    } else if args.command.as_deref() == Some("export-midi") {
+        // mandala export-midi <out.mid> [seconds]
+        let out = out("mandala export-midi <out.mid> [seconds]");
+        or_exit(mandala::midi::export_midi(
+            &planets,
+            out,
+            or_exit(seconds()),
+        ));
+    } else if args.command.as_deref() == Some("render") {
+        // mandala render <out.mp4> [seconds]
+        let out = out("mandala render <out.mp4> [seconds]");
+        or_exit(mandala::render::render_video(
+            &planets,
+            out,
+            or_exit(seconds()),
+            settings,
+        ));
     } else {
+        // before the window opens, so a bad log path or MIDI port doesn't flash one up
+        #[cfg(not(target_arch = "wasm32"))]
+        let outputs = or_exit(args.outputs(&planets));
         let ctx = sdl2::init().unwrap();
         let video_ctx = ctx.video().unwrap();
 
         let window = match video_ctx
             .window("Mandala", 800, 600)
             .position_centered()
+            .resizable()
+            .allow_highdpi()
             .opengl()
             .build()
         {
@@ -26,12 +98,25 @@ fn main() {
         let canvas = Rc::new(RefCell::new(canvas));
 
         #[cfg(not(target_arch = "wasm32"))]
-        mandala::main_loop(Rc::clone(&ctx), Rc::clone(&canvas)).unwrap();
+        or_exit(mandala::main_loop(
+            Rc::clone(&ctx),
+            Rc::clone(&canvas),
+            &planets,
+            settings,
+            outputs,
+        ));
 
         #[cfg(target_arch = "wasm32")]
         {
             mandala::emscripten::set_main_loop_callback(move || {
-                mandala::main_loop(Rc::clone(&ctx), Rc::clone(&canvas)).unwrap();
+                or_exit(mandala::main_loop(
+                    Rc::clone(&ctx),
+                    Rc::clone(&canvas),
+                    &planets,
+                    settings.clone(),
+                    // no files or MIDI ports in the browser
+                    Default::default(),
+                ));
             });
         }
     }
@@ -0,0 +1,221 @@
+/// Standard MIDI File export, one track per planet so the piece can be re-orchestrated in a DAW
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::model::{Planet, Sample};
+use crate::simulation::{self, Simulation};
+use crate::synth;
+
+/// Ticks per quarter note
+const DIVISION: u16 = 960;
+/// 120 bpm, every planet has its own tempo so the file just keeps time in seconds
+const TEMPO: u32 = 500_000;
+const TICKS_PER_SECOND: f64 = DIVISION as f64 * 1_000_000.0 / TEMPO as f64;
+/// Semitones either way at full pitch bend, set on every channel with RPN 0
+const BEND_RANGE: u8 = 2;
+/// Channel 10 is drums on General MIDI synths, so planets skip it
+const DRUMS: u8 = 9;
+/// One planet per channel besides the drums
+pub const MAX_PLANETS: usize = 15;
+
+/// What `sample` actually sounds at, `None` for sample files since there's no telling
+fn sounding(sample: &Sample) -> Option<f64> {
+    match sample {
+        Sample::Embedded(note) => Some(note.frequency()),
+        Sample::Synth(tone) => Some(tone.frequency() as f64),
+        Sample::Resampled(sample, factor) => sounding(sample).map(|f| f * factor),
+        Sample::File(_) => None,
+    }
+}
+
+/// Pitch of planet `index` in Hz. Embedded notes count as the pure harmonic they stand for rather than the
+/// equal tempered WAV, and planets playing sample files get the harmonic their position in the system gives them.
+fn frequency(planet: &Planet, index: usize) -> f64 {
+    match &planet.sample {
+        Sample::Embedded(note) => synth::FUNDAMENTAL as f64 * note.harmonic() as f64,
+        sample => sounding(sample).unwrap_or(synth::FUNDAMENTAL as f64 * (index + 1) as f64),
+    }
+}
+
+/// Nearest MIDI note to `frequency` and the pitch bend that makes up the difference, 8192 being no bend
+pub fn note_and_bend(frequency: f64) -> (u8, u16) {
+    let pitch = (69.0 + 12.0 * (frequency / 440.0).log2()).clamp(0.0, 127.0);
+    let note = pitch.round();
+    let bend = 8192.0 + (pitch - note) / BEND_RANGE as f64 * 8192.0;
+    (note as u8, bend.round().clamp(0.0, 16383.0) as u16)
+}
+
+/// MIDI channel for planet `index`, an error past [`MAX_PLANETS`] rather than doubling planets up on a channel
+pub fn channel(index: usize) -> Result<u8, String> {
+    match index {
+        i if i >= MAX_PLANETS => Err(format!(
+            "MIDI only has channels for {} planets, planet {} doesn't get one",
+            MAX_PLANETS,
+            i + 1
+        )),
+        i if i as u8 >= DRUMS => Ok(i as u8 + 1),
+        i => Ok(i as u8),
+    }
+}
+
+fn ticks(seconds: f64) -> u32 {
+    (seconds * TICKS_PER_SECOND).round() as u32
+}
+
+/// Events of one track chunk, timed in absolute ticks until they're written out
+#[derive(Default)]
+struct Track {
+    events: Vec<(u32, Vec<u8>)>,
+}
+
+impl Track {
+    fn push(&mut self, tick: u32, event: impl Into<Vec<u8>>) {
+        self.events.push((tick, event.into()));
+    }
+
+    fn name(&mut self, name: &str) {
+        let mut event = vec![0xFF, 0x03];
+        write_varlen(&mut event, name.len() as u32);
+        event.extend_from_slice(name.as_bytes());
+        self.push(0, event);
+    }
+
+    /// The chunk with delta times and an end of track, events must already be in order
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut last = 0;
+        for (tick, event) in &self.events {
+            write_varlen(&mut data, tick - last);
+            data.extend_from_slice(event);
+            last = *tick;
+        }
+        data.extend_from_slice(&[0x00, 0xFF, 0x2F, 0x00]);
+        let mut chunk = b"MTrk".to_vec();
+        chunk.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        chunk.extend_from_slice(&data);
+        chunk
+    }
+}
+
+/// MIDI's variable length quantity, 7 bits a byte with the high bit set on all but the last
+pub fn write_varlen(out: &mut Vec<u8>, value: u32) {
+    let mut bytes = vec![(value & 0x7F) as u8];
+    let mut value = value >> 7;
+    while value > 0 {
+        bytes.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    out.extend(bytes.iter().rev());
+}
+
+/// How a planet comes out over MIDI, the same in the exported file and live
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Voicing {
+    channel: u8,
+    note: u8,
+    bend: u16,
+    velocity: u8,
+    pan: u8,
+}
+
+impl Voicing {
+    /// Planet `index`'s channel and pitch, velocity and pan going by its mixer strip
+    pub(crate) fn new(planet: &Planet, index: usize) -> Result<Self, String> {
+        let (note, bend) = note_and_bend(frequency(planet, index));
+        Ok(Self {
+            channel: channel(index)?,
+            note,
+            bend,
+            velocity: (100.0 * planet.strip.gain).round().clamp(1.0, 127.0) as u8,
+            pan: (64.0 + 63.5 * planet.strip.pan).round().clamp(0.0, 127.0) as u8,
+        })
+    }
+
+    /// What goes out on the channel before any notes: bend range, pan and the bend itself
+    pub(crate) fn setup(&self) -> Vec<[u8; 3]> {
+        let control = |number: u8, value: u8| [0xB0 | self.channel, number, value];
+        // RPN 0 is the pitch bend range, then RPN null so stray data entries don't change it
+        let mut messages: Vec<[u8; 3]> = [
+            (101, 0),
+            (100, 0),
+            (6, BEND_RANGE),
+            (38, 0),
+            (101, 127),
+            (100, 127),
+            (10, self.pan),
+        ]
+        .into_iter()
+        .map(|(number, value)| control(number, value))
+        .collect();
+        messages.push([
+            0xE0 | self.channel,
+            (self.bend & 0x7F) as u8,
+            (self.bend >> 7) as u8,
+        ]);
+        messages
+    }
+
+    pub(crate) fn note_on(&self) -> [u8; 3] {
+        [0x90 | self.channel, self.note, self.velocity]
+    }
+
+    pub(crate) fn note_off(&self) -> [u8; 3] {
+        [0x80 | self.channel, self.note, 0]
+    }
+}
+
+/// Seconds a planet's MIDI notes last: as long as its sample, but never into its next note
+pub(crate) fn note_length(planet: &Planet) -> Result<f64, String> {
+    let pcm = planet
+        .sample
+        .pcm()
+        .map_err(|e| format!("{}'s sample: {}", planet.name, e))?;
+    Ok((pcm.samples.len() as f64 / pcm.sample_rate as f64).min(planet.period()))
+}
+
+/// Planet `index`'s track: its name and channel setup up front, then a note every revolution for `seconds`
+fn planet_track(planet: &Planet, index: usize, seconds: f64) -> Result<Track, String> {
+    let length = note_length(planet)?;
+    let voicing = Voicing::new(planet, index)?;
+
+    let mut track = Track::default();
+    track.name(&planet.name);
+    for message in voicing.setup() {
+        track.push(0, message);
+    }
+    let mut simulation = Simulation::new(vec![planet.period()]);
+    for trigger in simulation.advance(seconds) {
+        let next = simulation::trigger_seconds(planet.period(), trigger.revolution + 1);
+        let end = (trigger.seconds + length).min(next).min(seconds);
+        track.push(ticks(trigger.seconds), voicing.note_on());
+        track.push(ticks(end), voicing.note_off());
+    }
+    Ok(track)
+}
+
+/// Writes `seconds` of the piece to `path` as a type 1 SMF: a tempo track, then one track per planet
+pub fn export_midi(planets: &[Planet], path: &str, seconds: f64) -> Result<(), String> {
+    let mut tempo = Track::default();
+    tempo.name("Mandala");
+    let mut event = vec![0xFF, 0x51, 0x03];
+    event.extend_from_slice(&TEMPO.to_be_bytes()[1..]);
+    tempo.push(0, event);
+    let mut tracks = vec![tempo];
+    for (i, planet) in planets.iter().enumerate() {
+        tracks.push(planet_track(planet, i, seconds)?);
+    }
+
+    let mut smf = b"MThd".to_vec();
+    smf.extend_from_slice(&6u32.to_be_bytes());
+    smf.extend_from_slice(&1u16.to_be_bytes());
+    smf.extend_from_slice(&(tracks.len() as u16).to_be_bytes());
+    smf.extend_from_slice(&DIVISION.to_be_bytes());
+    for track in &tracks {
+        smf.extend_from_slice(&track.to_bytes());
+    }
+
+    let file = File::create(path).map_err(|e| format!("failed to create {}: {}", path, e))?;
+    let mut out = BufWriter::new(file);
+    out.write_all(&smf).map_err(|e| e.to_string())?;
+    out.flush().map_err(|e| e.to_string())
+}
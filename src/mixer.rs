@@ -0,0 +1,235 @@
+/// Per-planet mixer strips, so the slow outer planets can be balanced against mercury's constant pinging
+use crate::model::Planet;
+use crate::simulation;
+
+/// 1.0 is the original mix, this is as loud as SDL_mixer's channel volume goes from there
+pub const MAX_MASTER: f32 = 1.25;
+/// As far as the keyboard turns a strip up, system files can go further
+pub const MAX_GAIN: f32 = 2.0;
+
+/// One planet's level and place in the stereo field
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Strip {
+    pub gain: f32,
+    /// -1.0 is hard left, 1.0 hard right
+    pub pan: f32,
+    pub muted: bool,
+}
+
+impl Default for Strip {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            pan: 0.0,
+            muted: false,
+        }
+    }
+}
+
+impl Strip {
+    /// Left and right gain. It's a balance law, panning only ever turns the far side down,
+    /// so a centered strip sounds exactly like the unpanned mix did.
+    pub fn stereo(&self) -> (f32, f32) {
+        let pan = self.pan.clamp(-1.0, 1.0);
+        (
+            self.gain * (1.0 - pan).min(1.0),
+            self.gain * (1.0 + pan).min(1.0),
+        )
+    }
+}
+
+/// Placing notes in the stereo field by where their planet is on screen
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Spatial {
+    /// only the strips' own pan
+    #[default]
+    Off,
+    /// left/right follows the planet's x position
+    Pan,
+    /// and planets further from the listener, who sits at the bottom of the outermost orbit, are quieter
+    Distance,
+}
+
+impl Spatial {
+    /// Pan offset and gain for a planet on an orbit of `radius` at `angle`, `outermost` being the biggest orbit.
+    /// Uses the same angle as the renderer, so `x = cos(angle)` and screen y grows downwards.
+    pub fn place(self, radius: f32, outermost: f32, angle: f32) -> (f32, f32) {
+        let (x, y) = (radius * angle.cos(), radius * angle.sin());
+        match self {
+            Spatial::Off => (0.0, 1.0),
+            Spatial::Pan => (x / outermost, 1.0),
+            Spatial::Distance => {
+                let distance = x.hypot(outermost - y);
+                (x / outermost, outermost / (outermost + distance))
+            }
+        }
+    }
+
+    /// Off, pan, distance, off...
+    pub fn next(self) -> Self {
+        match self {
+            Spatial::Off => Spatial::Pan,
+            Spatial::Pan => Spatial::Distance,
+            Spatial::Distance => Spatial::Off,
+        }
+    }
+}
+
+/// Where a planet's orbit is, for [`Spatial`] placement
+#[derive(Debug, Clone, Copy)]
+struct Orbit {
+    radius: f32,
+    period: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Mixer {
+    strips: Vec<Strip>,
+    solo: Option<usize>,
+    /// master volume on top of every strip
+    master: f32,
+    spatial: Spatial,
+    /// empty unless built from planets, spatial placement is skipped without them
+    orbits: Vec<Orbit>,
+    /// the strip the gain and pan keys change
+    selected: usize,
+}
+
+impl Mixer {
+    pub fn new(strips: Vec<Strip>) -> Self {
+        Self {
+            strips,
+            solo: None,
+            master: 1.0,
+            spatial: Spatial::Off,
+            orbits: Vec::new(),
+            selected: 0,
+        }
+    }
+
+    /// Starts from the strip every planet was configured with
+    pub fn for_planets(planets: &[Planet]) -> Self {
+        Self {
+            orbits: planets
+                .iter()
+                .map(|planet| Orbit {
+                    radius: planet.orbit_radius,
+                    period: planet.period(),
+                })
+                .collect(),
+            ..Self::new(planets.iter().map(|planet| planet.strip).collect())
+        }
+    }
+
+    pub fn strips(&self) -> &[Strip] {
+        &self.strips
+    }
+
+    pub fn strip_mut(&mut self, planet: usize) -> Option<&mut Strip> {
+        self.strips.get_mut(planet)
+    }
+
+    pub fn master(&self) -> f32 {
+        self.master
+    }
+
+    pub fn set_master(&mut self, master: f32) {
+        self.master = master.clamp(0.0, MAX_MASTER);
+    }
+
+    pub fn spatial(&self) -> Spatial {
+        self.spatial
+    }
+
+    pub fn set_spatial(&mut self, spatial: Spatial) {
+        self.spatial = spatial;
+    }
+
+    pub fn solo(&self) -> Option<usize> {
+        self.solo
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Moves the selection `step` strips along, wrapping around at either end
+    pub fn select(&mut self, step: isize) {
+        let strips = self.strips.len() as isize;
+        if strips > 0 {
+            self.selected = (self.selected as isize + step).rem_euclid(strips) as usize;
+        }
+    }
+
+    /// Turns the selected strip up or down, never past [`MAX_GAIN`] unless it started out louder
+    pub fn nudge_gain(&mut self, step: f32) {
+        if let Some(strip) = self.strips.get_mut(self.selected) {
+            strip.gain = (strip.gain + step).clamp(0.0, MAX_GAIN.max(strip.gain));
+        }
+    }
+
+    /// Moves the selected strip left (negative) or right
+    pub fn nudge_pan(&mut self, step: f32) {
+        if let Some(strip) = self.strips.get_mut(self.selected) {
+            strip.pan = (strip.pan + step).clamp(-1.0, 1.0);
+        }
+    }
+
+    pub fn toggle_mute(&mut self, planet: usize) {
+        if let Some(strip) = self.strips.get_mut(planet) {
+            strip.muted = !strip.muted;
+        }
+    }
+
+    /// Solos `planet`, or un-solos it if it already was
+    pub fn toggle_solo(&mut self, planet: usize) {
+        if planet < self.strips.len() {
+            self.solo = if self.solo == Some(planet) {
+                None
+            } else {
+                Some(planet)
+            };
+        }
+    }
+
+    /// A soloed planet is the only one heard, otherwise everything that isn't muted is
+    pub fn audible(&self, planet: usize) -> bool {
+        match self.solo {
+            Some(solo) => solo == planet,
+            None => self.strips.get(planet).is_some_and(|strip| !strip.muted),
+        }
+    }
+
+    /// Left and right gain for `planet` with mute, solo and the master volume applied
+    pub fn gains(&self, planet: usize) -> (f32, f32) {
+        if !self.audible(planet) {
+            return (0.0, 0.0);
+        }
+        let (left, right) = self.strips[planet].stereo();
+        (left * self.master, right * self.master)
+    }
+
+    /// Like [`Mixer::gains`], but placed wherever `planet` is on its orbit `seconds` into the piece
+    pub fn gains_at(&self, planet: usize, seconds: f64) -> (f32, f32) {
+        let Some(orbit) = self.orbits.get(planet) else {
+            return self.gains(planet);
+        };
+        if self.spatial == Spatial::Off || !self.audible(planet) {
+            return self.gains(planet);
+        }
+        let outermost = self
+            .orbits
+            .iter()
+            .map(|orbit| orbit.radius)
+            .fold(0.0, f32::max);
+        let angle = simulation::angle(orbit.period, seconds) as f32;
+        let (offset, attenuation) = self.spatial.place(orbit.radius, outermost, angle);
+        let strip = Strip {
+            pan: self.strips[planet].pan + offset,
+            ..self.strips[planet]
+        };
+        let (left, right) = strip.stereo();
+        let gain = self.master * attenuation;
+        (left * gain, right * gain)
+    }
+}
@@ -0,0 +1,341 @@
+/// The planets, their notes and tempos, shared by the single and multi threaded front ends
+use sdl2::pixels::Color;
+
+use crate::formats;
+use crate::layout;
+use crate::mixer::Strip;
+use crate::sequencer::SAMPLE_RATE;
+use crate::synth::{self, Tone};
+use crate::tempo::{Tempo, ORIGINAL_TEMPO};
+use crate::tuning;
+use crate::wav::{self, Pcm, SampleFormat, WavWriter};
+use std::{borrow::Cow, io::Cursor, path::PathBuf, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Note {
+    Mercury,
+    Venus,
+    Earth,
+    Mars,
+    Jupiter,
+    Saturn,
+    Uranus,
+    Neptune,
+    Pluto,
+}
+
+impl Note {
+    pub const ALL: [Note; 9] = [
+        Note::Mercury,
+        Note::Venus,
+        Note::Earth,
+        Note::Mars,
+        Note::Jupiter,
+        Note::Saturn,
+        Note::Uranus,
+        Note::Neptune,
+        Note::Pluto,
+    ];
+
+    /// Which harmonic of the series the note is, mercury is the fundamental
+    pub fn harmonic(self) -> u32 {
+        Note::ALL.iter().position(|&note| note == self).unwrap() as u32 + 1
+    }
+
+    /// Pitch of the embedded WAV, they're the harmonics of [`crate::synth::FUNDAMENTAL`] rounded to equal temperament
+    pub fn frequency(self) -> f64 {
+        synth::FUNDAMENTAL as f64 * tuning::equal_tempered(self.harmonic())
+    }
+
+    pub fn to_bytes(self) -> &'static [u8] {
+        match self {
+            Note::Mercury => rotation_around_sun_days::MERCURY_NOTE,
+            Note::Venus => rotation_around_sun_days::VENUS_NOTE,
+            Note::Earth => rotation_around_sun_days::EARTH_NOTE,
+            Note::Mars => rotation_around_sun_days::MARS_NOTE,
+            Note::Jupiter => rotation_around_sun_days::JUPITER_NOTE,
+            Note::Saturn => rotation_around_sun_days::SATURN_NOTE,
+            Note::Uranus => rotation_around_sun_days::URANUS_NOTE,
+            Note::Neptune => rotation_around_sun_days::NEPTUNE_NOTE,
+            Note::Pluto => rotation_around_sun_days::PLUTO_NOTE,
+        }
+    }
+}
+
+impl FromStr for Note {
+    type Err = String;
+
+    /// Case-insensitive planet name, e.g. `"venus"`
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Note::ALL
+            .into_iter()
+            .find(|note| format!("{:?}", note).eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("no embedded note called '{}'", name))
+    }
+}
+
+/// Where a planet's note comes from
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sample {
+    /// One of the harmonic series notes baked into the binary
+    Embedded(Note),
+    /// A WAV, OGG, FLAC or MP3 file on disk
+    File(PathBuf),
+    /// Generated by the built-in synth
+    Synth(Tone),
+    /// Another sample played back `factor` times faster, and that much higher
+    Resampled(Box<Sample>, f64),
+}
+
+impl Sample {
+    /// File contents, generated notes get rendered into an in-memory 32-bit float WAV
+    pub fn bytes(&self) -> Result<Cow<'static, [u8]>, String> {
+        match self {
+            Sample::Embedded(note) => Ok(Cow::Borrowed(note.to_bytes())),
+            Sample::File(path) => std::fs::read(path)
+                .map(Cow::Owned)
+                .map_err(|e| format!("failed to read {}: {}", path.display(), e)),
+            Sample::Synth(_) | Sample::Resampled(..) => {
+                let pcm = self.pcm()?;
+                let mut writer = WavWriter::new(
+                    Cursor::new(Vec::new()),
+                    pcm.sample_rate,
+                    1,
+                    SampleFormat::F32,
+                )?;
+                writer.write(&pcm.samples)?;
+                Ok(Cow::Owned(writer.finish()?.into_inner()))
+            }
+        }
+    }
+
+    /// The decoded note, generated notes skip the round trip through WAV
+    pub fn pcm(&self) -> Result<Pcm, String> {
+        match self {
+            Sample::Synth(tone) => Ok(Pcm {
+                sample_rate: SAMPLE_RATE,
+                samples: tone.render(SAMPLE_RATE),
+            }),
+            Sample::Resampled(sample, factor) => {
+                let pcm = sample.pcm()?;
+                Ok(Pcm {
+                    samples: tuning::resample(&pcm.samples, *factor)?,
+                    ..pcm
+                })
+            }
+            Sample::Embedded(note) => wav::decode(note.to_bytes()),
+            Sample::File(path) => {
+                formats::decode(&self.bytes()?).map_err(|e| format!("{}: {}", path.display(), e))
+            }
+        }
+    }
+
+    /// The same sample at `frequency` Hz: synth notes are simply played at that pitch, embedded notes get resampled.
+    /// Sample files come back as they are since there's no telling what pitch they're at.
+    pub fn retuned(&self, frequency: f64) -> Self {
+        match self {
+            Sample::Embedded(note) => {
+                Sample::Resampled(Box::new(self.clone()), frequency / note.frequency())
+            }
+            Sample::Synth(tone) => Sample::Synth(Tone {
+                fundamental: frequency as f32,
+                harmonic: 1,
+                ..tone.clone()
+            }),
+            Sample::File(_) => self.clone(),
+            Sample::Resampled(sample, _) => sample.retuned(frequency),
+        }
+    }
+
+    /// The same note made by the synth instead, embedded notes being harmonics of [`crate::synth::FUNDAMENTAL`]
+    pub fn synthesized(&self) -> Self {
+        match self {
+            Sample::Embedded(note) => Sample::Synth(Tone::harmonic(note.harmonic())),
+            _ => self.clone(),
+        }
+    }
+}
+
+pub mod rotation_around_sun_days {
+    pub const MERCURY_FACTOR: f32 = 88.0;
+    pub const VENUS_FACTOR: f32 = 224.7;
+    pub const EARTH_FACTOR: f32 = 365.256;
+    pub const MARS_FACTOR: f32 = 686.93;
+    pub const JUPITER_FACTOR: f32 = 4_333.0;
+    pub const SATURN_FACTOR: f32 = 10_756.0;
+    pub const URANUS_FACTOR: f32 = 30_687.0;
+    pub const NEPTUNE_FACTOR: f32 = 60_190.0;
+    pub const PLUTO_FACTOR: f32 = 248.0 * EARTH_FACTOR;
+
+    /// everything is relative to mercury
+    pub const MERCURY: f32 = 1.0;
+    pub const VENUS: f32 = VENUS_FACTOR / MERCURY_FACTOR;
+    pub const EARTH: f32 = EARTH_FACTOR / MERCURY_FACTOR;
+    pub const MARS: f32 = MARS_FACTOR / MERCURY_FACTOR;
+    pub const JUPITER: f32 = JUPITER_FACTOR / MERCURY_FACTOR;
+    pub const SATURN: f32 = SATURN_FACTOR / MERCURY_FACTOR;
+    pub const URANUS: f32 = URANUS_FACTOR / MERCURY_FACTOR;
+    pub const NEPTUNE: f32 = NEPTUNE_FACTOR / MERCURY_FACTOR;
+    pub const PLUTO: f32 = PLUTO_FACTOR / MERCURY_FACTOR;
+
+    pub const MERCURY_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_1_61Hz.wav");
+    pub const VENUS_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_2_123Hz.wav");
+    pub const EARTH_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_3_185Hz.wav");
+    pub const MARS_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_4_246Hz.wav");
+    pub const JUPITER_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_5_311Hz.wav");
+    pub const SATURN_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_6_370Hz.wav");
+    pub const URANUS_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_7_440Hz.wav");
+    pub const NEPTUNE_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_8_493Hz.wav");
+    pub const PLUTO_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_9_554Hz.wav");
+
+    pub const MERCURY_ORIGINAL_BPM: f32 = 124.56;
+    pub const VENUS_ORIGINAL_BPM: f32 = 48.766;
+    pub const EARTH_ORIGINAL_BPM: f32 = 30.0;
+    pub const MARS_ORIGINAL_BPM: f32 = 15.9505;
+    pub const JUPITER_ORIGINAL_BPM: f32 = 2.52913;
+    pub const SATURN_ORIGINAL_BPM: f32 = 1.01845;
+    pub const URANUS_ORIGINAL_BPM: f32 = 0.3571;
+    pub const NEPTUNE_ORIGINAL_BPM: f32 = 0.1821;
+    pub const PLUTO_ORIGINAL_BPM: f32 = 0.12113;
+
+    /// Orbital periods in days, mercury to pluto
+    pub const FACTORS: [f32; 9] = [
+        MERCURY_FACTOR,
+        VENUS_FACTOR,
+        EARTH_FACTOR,
+        MARS_FACTOR,
+        JUPITER_FACTOR,
+        SATURN_FACTOR,
+        URANUS_FACTOR,
+        NEPTUNE_FACTOR,
+        PLUTO_FACTOR,
+    ];
+
+    /// The hand-tuned tempos the piece was written with, mercury to pluto
+    pub const ORIGINAL_BPMS: [f32; 9] = [
+        MERCURY_ORIGINAL_BPM,
+        VENUS_ORIGINAL_BPM,
+        EARTH_ORIGINAL_BPM,
+        MARS_ORIGINAL_BPM,
+        JUPITER_ORIGINAL_BPM,
+        SATURN_ORIGINAL_BPM,
+        URANUS_ORIGINAL_BPM,
+        NEPTUNE_ORIGINAL_BPM,
+        PLUTO_ORIGINAL_BPM,
+    ];
+}
+
+/// A planet as both front ends see it: which note it plays, how fast it orbits and how it's drawn
+#[derive(Debug, Clone)]
+pub struct Planet {
+    pub name: String,
+    pub sample: Sample,
+    pub bpm: f32,
+    /// normalized, 1.0 reaches the edge of the window's shorter side
+    pub orbit_radius: f32,
+    pub color: Color,
+    /// real diameter in km, for drawing planets to scale
+    pub diameter: Option<f32>,
+    /// where it starts out on the mixer
+    pub strip: Strip,
+}
+
+impl Planet {
+    /// Seconds per revolution, one note per revolution
+    pub fn period(&self) -> f64 {
+        60.0 / self.bpm as f64
+    }
+}
+
+/// The nine planets the piece is written for
+pub fn solar_system() -> Vec<Planet> {
+    solar_system_at(ORIGINAL_TEMPO)
+}
+
+/// The solar system with every planet's tempo worked out from its orbital period by `tempo`
+pub fn solar_system_at(tempo: Tempo) -> Vec<Planet> {
+    let bpms = tempo.bpms(&rotation_around_sun_days::FACTORS);
+    vec![
+        Planet {
+            name: "Mercury".to_string(),
+            sample: Sample::Embedded(Note::Mercury),
+            bpm: bpms[0],
+            orbit_radius: layout::from_pixels(50.0),
+            color: Color::GREY,
+            diameter: Some(4879.0),
+            strip: Strip::default(),
+        },
+        Planet {
+            name: "Venus".to_string(),
+            sample: Sample::Embedded(Note::Venus),
+            bpm: bpms[1],
+            orbit_radius: layout::from_pixels(75.0),
+            color: Color::RGB(255, 165, 0),
+            diameter: Some(12104.0),
+            strip: Strip::default(),
+        },
+        Planet {
+            name: "Earth".to_string(),
+            sample: Sample::Embedded(Note::Earth),
+            bpm: bpms[2],
+            orbit_radius: layout::from_pixels(100.0),
+            color: Color::RGB(0, 0, 255),
+            diameter: Some(12756.0),
+            strip: Strip::default(),
+        },
+        Planet {
+            name: "Mars".to_string(),
+            sample: Sample::Embedded(Note::Mars),
+            bpm: bpms[3],
+            orbit_radius: layout::from_pixels(125.0),
+            color: Color::RGB(255, 100, 0),
+            diameter: Some(6792.0),
+            strip: Strip::default(),
+        },
+        Planet {
+            name: "Jupiter".to_string(),
+            sample: Sample::Embedded(Note::Jupiter),
+            bpm: bpms[4],
+            orbit_radius: layout::from_pixels(150.0),
+            color: Color::RGB(218, 165, 200),
+            diameter: Some(142984.0),
+            strip: Strip::default(),
+        },
+        Planet {
+            name: "Saturn".to_string(),
+            sample: Sample::Embedded(Note::Saturn),
+            bpm: bpms[5],
+            orbit_radius: layout::from_pixels(175.0),
+            color: Color::RGB(210, 180, 140),
+            diameter: Some(120536.0),
+            strip: Strip::default(),
+        },
+        Planet {
+            name: "Uranus".to_string(),
+            sample: Sample::Embedded(Note::Uranus),
+            bpm: bpms[6],
+            orbit_radius: layout::from_pixels(200.0),
+            color: Color::RGB(0, 255, 255),
+            diameter: Some(51118.0),
+            strip: Strip::default(),
+        },
+        Planet {
+            name: "Neptune".to_string(),
+            sample: Sample::Embedded(Note::Neptune),
+            bpm: bpms[7],
+            orbit_radius: layout::from_pixels(225.0),
+            color: Color::RGB(0, 0, 139),
+            diameter: Some(49528.0),
+            strip: Strip::default(),
+        },
+        Planet {
+            name: "Pluto".to_string(),
+            sample: Sample::Embedded(Note::Pluto),
+            bpm: bpms[8],
+            orbit_radius: layout::from_pixels(250.0),
+            color: Color::RGB(128, 128, 128),
+            diameter: Some(2376.0),
+            strip: Strip::default(),
+        },
+    ]
+}
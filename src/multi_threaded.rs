@@ -1,271 +1,610 @@
+use sdl2::event::{Event, WindowEvent};
 use sdl2::mixer::LoaderRWops;
 use sdl2::{
     self,
     mixer::{Channel, AUDIO_F32, DEFAULT_CHANNELS},
     sys::SDL_Delay,
 };
+use std::collections::HashMap;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
 use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
-use std::{collections::HashMap, io::Write, process::exit};
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Note {
-    Mercury,
-    Venus,
-    Earth,
-    Mars,
-    Jupiter,
-    Saturn,
-    Uranus,
-    Neptune,
-    Pluto,
+
+use crate::cli::Settings;
+use crate::clock::Clock;
+use crate::controls::{self, Action};
+use crate::events::{NoteEvent, Outputs};
+use crate::formats;
+use crate::hud;
+use crate::layout::Layout;
+use crate::live_midi::LiveMidi;
+use crate::mixer::{Mixer, Spatial};
+use crate::model::Planet;
+use crate::pattern::PatternLayer;
+use crate::simulation;
+use crate::text;
+use crate::voices::{Admit, Loudness, Polyphony, Ringing, VoiceStats};
+use crate::{draw_scene, toggle_fullscreen};
+
+/// How late a planet's triggers went out compared to their absolute deadlines
+#[derive(Debug, Default, Clone, Copy)]
+struct Drift {
+    triggers: u32,
+    last: Duration,
+    max: Duration,
+    total: Duration,
 }
 
-impl Note {
-    fn to_bytes(&self) -> &'static [u8] {
-        match self {
-            Note::Mercury => rotation_around_sun_days::MERCURY_NOTE,
-            Note::Venus => rotation_around_sun_days::VENUS_NOTE,
-            Note::Earth => rotation_around_sun_days::EARTH_NOTE,
-            Note::Mars => rotation_around_sun_days::MARS_NOTE,
-            Note::Jupiter => rotation_around_sun_days::JUPITER_NOTE,
-            Note::Saturn => rotation_around_sun_days::SATURN_NOTE,
-            Note::Uranus => rotation_around_sun_days::URANUS_NOTE,
-            Note::Neptune => rotation_around_sun_days::NEPTUNE_NOTE,
-            Note::Pluto => rotation_around_sun_days::PLUTO_NOTE,
-        }
+impl Drift {
+    fn record(&mut self, late: Duration) {
+        self.triggers += 1;
+        self.last = late;
+        self.max = self.max.max(late);
+        self.total += late;
+    }
+
+    fn mean(&self) -> Duration {
+        self.total / self.triggers.max(1)
     }
 }
 
-pub mod rotation_around_sun_days {
-    pub const MERCURY_FACTOR: f32 = 88.0;
-    pub const VENUS_FACTOR: f32 = 224.7;
-    pub const EARTH_FACTOR: f32 = 365.256;
-    pub const MARS_FACTOR: f32 = 686.93;
-    pub const JUPITER_FACTOR: f32 = 4_333.0;
-    pub const SATURN_FACTOR: f32 = 10_756.0;
-    pub const URANUS_FACTOR: f32 = 30_687.0;
-    pub const NEPTUNE_FACTOR: f32 = 60_190.0;
-    pub const PLUTO_FACTOR: f32 = (248.0 * EARTH_FACTOR) as f32;
-
-    pub const MERCURY: f32 = MERCURY_FACTOR / MERCURY_FACTOR;
-    pub const VENUS: f32 = VENUS_FACTOR / MERCURY_FACTOR;
-    pub const EARTH: f32 = EARTH_FACTOR / MERCURY_FACTOR;
-    pub const MARS: f32 = MARS_FACTOR / MERCURY_FACTOR;
-    pub const JUPITER: f32 = JUPITER_FACTOR / MERCURY_FACTOR;
-    pub const SATURN: f32 = SATURN_FACTOR / MERCURY_FACTOR;
-    pub const URANUS: f32 = URANUS_FACTOR / MERCURY_FACTOR;
-    pub const NEPTUNE: f32 = NEPTUNE_FACTOR / MERCURY_FACTOR;
-    pub const PLUTO: f32 = PLUTO_FACTOR / MERCURY_FACTOR;
-
-    pub const MERCURY_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_1_61Hz.wav");
-    pub const VENUS_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_2_123Hz.wav");
-    pub const EARTH_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_3_185Hz.wav");
-    pub const MARS_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_4_246Hz.wav");
-    pub const JUPITER_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_5_311Hz.wav");
-    pub const SATURN_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_6_370Hz.wav");
-    pub const URANUS_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_7_440Hz.wav");
-    pub const NEPTUNE_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_8_493Hz.wav");
-    pub const PLUTO_NOTE: &[u8] = include_bytes!("../notes/harmonic_series_9_554Hz.wav");
-
-    pub const MERCURY_ORIGINAL_BPM: f32 = 124.56;
-    pub const VENUS_ORIGINAL_BPM: f32 = 48.766;
-    pub const EARTH_ORIGINAL_BPM: f32 = 30.0;
-    pub const MARS_ORIGINAL_BPM: f32 = 15.9505;
-    pub const JUPITER_ORIGINAL_BPM: f32 = 2.52913;
-    pub const SATURN_ORIGINAL_BPM: f32 = 1.01845;
-    pub const URANUS_ORIGINAL_BPM: f32 = 0.3571;
-    pub const NEPTUNE_ORIGINAL_BPM: f32 = 0.1821;
-    pub const PLUTO_ORIGINAL_BPM: f32 = 0.12113;
+/// Piece time in the multi threaded player: where the clock was at `anchor`, and how fast it's moved since
+#[derive(Debug, Clone, Copy)]
+struct Timeline {
+    clock: Clock,
+    anchor: Instant,
+    /// piece seconds after which the planet threads stop triggering, see [`crate::alignment::last_note`]
+    end: Option<f64>,
 }
 
-struct Planet {
-    note: Note,
-    bpm: f32,
-    orbit_radius: i32,
-    color: sdl2::pixels::Color,
+impl Timeline {
+    fn new(clock: Clock, end: Option<f64>) -> Self {
+        Self {
+            clock,
+            anchor: Instant::now(),
+            end,
+        }
+    }
+
+    /// Seconds into the piece
+    fn elapsed(&self) -> f64 {
+        self.at(Instant::now())
+    }
+
+    /// Seconds into the piece at `instant`, which must not be before the anchor
+    fn at(&self, instant: Instant) -> f64 {
+        self.clock.seconds()
+            + self.clock.rate() * instant.saturating_duration_since(self.anchor).as_secs_f64()
+    }
+
+    /// A new timeline starting from where this one is now, with `change` applied to its clock
+    fn rebase(&self, change: impl FnOnce(&mut Clock)) -> Self {
+        let mut clock = self.clock;
+        clock.seek(self.elapsed());
+        change(&mut clock);
+        Self::new(clock, self.end)
+    }
+
+    /// When the piece reaches `seconds`, which must not be behind the clock
+    fn instant_of(&self, seconds: f64) -> Instant {
+        self.anchor
+            + Duration::from_secs_f64(
+                (seconds - self.clock.seconds()).max(0.0) / self.clock.speed(),
+            )
+    }
 }
 
+/// Fires `planet`'s note on every revolution from where `timeline` starts, each at its absolute deadline
+/// so send latency and sleep overshoot never carry over to the next trigger.
+/// Runs until `stop` receives anything or its sender is dropped, or the timeline ends.
 fn spawn_thread_planet(
-    bpm: f32,
-    note: Note,
-    main_audio_thread: std::sync::mpsc::Sender<Note>,
-    vis_state: Arc<Mutex<HashMap<Note, Instant>>>,
-) {
-    std::thread::spawn(move || loop {
-        {
-            let mut state = vis_state.lock().unwrap();
-            state.insert(note, Instant::now());
+    period: f64,
+    planet: usize,
+    timeline: Timeline,
+    main_audio_thread: Sender<NoteEvent>,
+    vis_state: Arc<Mutex<HashMap<usize, Instant>>>,
+    drift: Arc<Mutex<HashMap<usize, Drift>>>,
+    stop: Receiver<()>,
+) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        let first = simulation::next_revolution(period, timeline.clock.seconds());
+        for n in first.. {
+            let scheduled = simulation::trigger_seconds(period, n);
+            if timeline.end.is_some_and(|end| scheduled > end) {
+                break;
+            }
+            let deadline = timeline.instant_of(scheduled);
+            match stop.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Err(RecvTimeoutError::Timeout) => {}
+                _ => break,
+            }
+            let now = Instant::now();
+            {
+                let mut state = vis_state.lock().unwrap();
+                state.insert(planet, now);
+            }
+            drift
+                .lock()
+                .unwrap()
+                .entry(planet)
+                .or_default()
+                .record(now.saturating_duration_since(deadline));
+            let note = NoteEvent {
+                planet,
+                revolution: n,
+                scheduled,
+                actual: timeline.at(now),
+                voice: None,
+                speed: timeline.clock.speed(),
+            };
+            if main_audio_thread.send(note).is_err() {
+                break;
+            }
         }
-        main_audio_thread.send(note).unwrap();
-        std::thread::sleep(Duration::from_secs_f32(60.0 / bpm));
-    });
+    })
+}
+
+/// The running planet threads, stopped with [`PlanetThreads::stop`] instead of exiting the process
+#[derive(Default)]
+struct PlanetThreads {
+    stop: Vec<Sender<()>>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl PlanetThreads {
+    /// One thread per planet following `timeline`, none at all while it's paused
+    fn spawn(
+        planets: &[Planet],
+        timeline: Timeline,
+        main_audio: &Sender<NoteEvent>,
+        vis_state: &Arc<Mutex<HashMap<usize, Instant>>>,
+        drift: &Arc<Mutex<HashMap<usize, Drift>>>,
+    ) -> Self {
+        let mut threads = PlanetThreads::default();
+        if timeline.clock.paused() {
+            return threads;
+        }
+        for (i, planet) in planets.iter().enumerate() {
+            let (stop, stop_rx) = std::sync::mpsc::channel();
+            threads.stop.push(stop);
+            threads.handles.push(spawn_thread_planet(
+                planet.period(),
+                i,
+                timeline,
+                main_audio.clone(),
+                Arc::clone(vis_state),
+                Arc::clone(drift),
+                stop_rx,
+            ));
+        }
+        threads
+    }
+
+    /// Wakes every planet thread and waits for them to finish
+    fn stop(self) {
+        drop(self.stop);
+        for handle in self.handles {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// What `Channel::all().set_volume(100)` used to give every note, before the strip gain
+const CHANNEL_VOLUME: i32 = 100;
+/// A note on one of SDL_mixer's channels
+#[derive(Debug, Clone, Copy)]
+struct Playing {
+    planet: usize,
+    started: Instant,
+}
+
+/// Which planet's note every mixer channel is playing, channel `i` being slot `i`
+struct Channels {
+    slots: Vec<Option<Playing>>,
+    stats: VoiceStats,
+}
+
+impl Channels {
+    /// One SDL_mixer channel per voice
+    fn allocate(voices: usize) -> Self {
+        sdl2::mixer::allocate_channels(voices as i32);
+        Self {
+            slots: vec![None; voices],
+            stats: VoiceStats::default(),
+        }
+    }
+
+    /// Forgets notes that have finished on their own
+    fn reap(&mut self) {
+        for (i, slot) in self.slots.iter_mut().enumerate() {
+            if slot.is_some() && !Channel(i as i32).is_playing() {
+                *slot = None;
+            }
+        }
+    }
+
+    fn playing(&self) -> impl Iterator<Item = (Channel, Playing)> + '_ {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, slot)| slot.map(|playing| (Channel(i as i32), playing)))
+    }
+
+    /// A channel for a new note on `planet`, free or stolen as `polyphony` says, `None` if the note is dropped.
+    /// `level` is how loud a planet's note is that many seconds in.
+    fn claim(
+        &mut self,
+        planet: usize,
+        polyphony: Polyphony,
+        level: impl Fn(usize, f64) -> f32,
+    ) -> Option<Channel> {
+        self.reap();
+        let now = Instant::now();
+        let (channels, ringing): (Vec<usize>, Vec<Ringing>) = self
+            .playing()
+            .map(|(channel, playing)| {
+                let age = now.duration_since(playing.started).as_secs_f64();
+                let ringing = Ringing {
+                    planet: playing.planet,
+                    age,
+                    level: level(playing.planet, age),
+                };
+                (channel.0 as usize, ringing)
+            })
+            .unzip();
+        let admit = polyphony.admit(planet, &ringing);
+        let slot = match admit {
+            Admit::Play => self.slots.iter().position(Option::is_none),
+            Admit::Steal(stolen) => Some(channels[stolen]),
+            Admit::Drop => None,
+        };
+        let Some(slot) = slot else {
+            self.stats.record(Admit::Drop, ringing.len());
+            return None;
+        };
+        self.slots[slot] = Some(Playing {
+            planet,
+            started: now,
+        });
+        self.stats.record(admit, self.playing().count());
+        Some(Channel(slot as i32))
+    }
 }
 
-pub fn multi_threaded_main() -> Result<(), String> {
+/// Sets a channel's volume and panning to the mixer's left and right gain
+fn place(channel: Channel, (left, right): (f32, f32)) {
+    let loudest = left.max(right);
+    channel.set_volume((CHANNEL_VOLUME as f32 * loudest).round().min(128.0) as i32);
+    if loudest > 0.0 {
+        let _ = channel.set_panning(
+            (255.0 * left / loudest).round() as u8,
+            (255.0 * right / loudest).round() as u8,
+        );
+    }
+}
+
+pub fn multi_threaded_main(
+    planets: &[Planet],
+    settings: Settings,
+    mut outputs: Outputs,
+) -> Result<(), String> {
+    let Settings {
+        clock: start,
+        spatial,
+        polyphony,
+        mut view,
+        pattern,
+    } = settings;
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
-    let window = video_subsystem.window("Mandala", 800, 600).build().unwrap();
+    let window = video_subsystem
+        .window("Mandala", 800, 600)
+        .resizable()
+        .allow_highdpi()
+        .build()
+        .unwrap();
     let mut canvas = window.into_canvas().build().unwrap();
     let mut event_pump = sdl_context.event_pump().unwrap();
+    let ttf = sdl2::ttf::init().map_err(|e| e.to_string())?;
+    let mut layout = Layout::for_window(&canvas);
+    let mut font = text::load_font(&ttf, layout.font_size())?;
+    let texture_creator = canvas.texture_creator();
+    let size = canvas.output_size()?;
+    let mut pattern = pattern
+        .map(|pattern| PatternLayer::new(pattern, &texture_creator, size))
+        .transpose()?;
 
-    let _mixer = sdl2::mixer::init(sdl2::mixer::InitFlag::all()).unwrap();
-    sdl2::mixer::open_audio(44100, AUDIO_F32, DEFAULT_CHANNELS, 2048).unwrap();
-    sdl2::mixer::allocate_channels(20);
-    sdl2::mixer::Channel::all().set_volume(100);
+    let _mixer = sdl2::mixer::init(sdl2::mixer::InitFlag::all())?;
+    sdl2::mixer::open_audio(44100, AUDIO_F32, DEFAULT_CHANNELS, 2048)?;
 
-    let (main_audio, main_audio_rx) = std::sync::mpsc::channel::<Note>();
+    let (main_audio, main_audio_rx) = std::sync::mpsc::channel::<NoteEvent>();
+    // read and decode every sample up front so a bad file fails here rather than in the audio thread
+    let sample_bytes = planets
+        .iter()
+        .map(|planet| planet.sample.bytes())
+        .collect::<Result<Vec<_>, String>>()?;
+    let loudness = planets
+        .iter()
+        .zip(&sample_bytes)
+        .map(|(planet, bytes)| {
+            let pcm =
+                formats::decode(bytes).map_err(|e| format!("{}'s sample: {}", planet.name, e))?;
+            Ok((Loudness::new(&pcm.samples), pcm.sample_rate as f64))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let channels = Arc::new(Mutex::new(Channels::allocate(polyphony.voices)));
+    let mut mixer = Mixer::for_planets(planets);
+    mixer.set_spatial(spatial);
+    let mixer = Arc::new(Mutex::new(mixer));
 
-    std::thread::spawn(move || {
+    let audio_mixer = Arc::clone(&mixer);
+    let audio_channels = Arc::clone(&channels);
+    // triggers go out from the audio thread, position updates from the render loop
+    let mut positions = outputs.osc.clone();
+    let midi = outputs.midi.as_ref().map(LiveMidi::sender);
+    let audio_thread = std::thread::spawn(move || {
         let audio_channel = main_audio_rx;
         let mut preloaded_chunks = HashMap::new();
-        let mut max_channels_used = 0;
-        loop {
-            let note = audio_channel.recv().unwrap();
-            let chunk = preloaded_chunks.entry(note).or_insert_with(|| {
-                sdl2::rwops::RWops::from_bytes(note.to_bytes())
-                    .unwrap()
-                    .load_wav()
-                    .unwrap()
-            });
-            Channel::all().play(chunk, 0).unwrap();
-            max_channels_used = max_channels_used.max(sdl2::mixer::get_playing_channels_number());
-            print!(
-                "\rMax channels used: {} Playing channels: {}",
-                max_channels_used,
-                sdl2::mixer::get_playing_channels_number()
-            );
-            std::io::stdout().flush().unwrap();
+        // ends once every planet thread and the main loop have hung up
+        while let Ok(mut note) = audio_channel.recv() {
+            let (planet, seconds) = (note.planet, note.scheduled);
+            let mixer = audio_mixer.lock().unwrap();
+            let audible = mixer.audible(planet);
+            note.voice = 'play: {
+                let gains = mixer.gains_at(planet, seconds);
+                if !outputs.audio || gains.0.max(gains.1) <= 0.0 {
+                    break 'play None;
+                }
+                let chunk = preloaded_chunks.entry(planet).or_insert_with(|| {
+                    sdl2::rwops::RWops::from_bytes(&sample_bytes[planet])
+                        .and_then(|rwops| rwops.load_wav())
+                        .expect("sample already decoded at startup")
+                });
+                let mut channels = audio_channels.lock().unwrap();
+                let level = |planet: usize, age: f64| {
+                    let (left, right) = mixer.gains_at(planet, seconds);
+                    let (loudness, rate) = &loudness[planet];
+                    left.max(right) * loudness.at((age * rate) as usize)
+                };
+                let Some(channel) = channels.claim(planet, polyphony, level) else {
+                    break 'play None;
+                };
+                place(channel, gains);
+                if channel.play(chunk, 0).is_err() {
+                    channels.slots[channel.0 as usize] = None;
+                    channels.stats.dropped += 1;
+                    break 'play None;
+                }
+                Some(channel.0 as usize)
+            };
+            drop(mixer);
+            if let Err(e) = outputs.send(&note, audible) {
+                eprintln!("mandala: stopped writing the event log: {}", e);
+                outputs.log = None;
+            }
+        }
+        if let Err(e) = outputs.close() {
+            eprintln!("mandala: stopped writing the event log: {}", e);
         }
     });
 
-    let vis_state: Arc<Mutex<HashMap<Note, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
-
-    let planets = vec![
-        Planet {
-            note: Note::Mercury,
-            bpm: rotation_around_sun_days::MERCURY_ORIGINAL_BPM,
-            orbit_radius: 50,
-            color: sdl2::pixels::Color::GREY,
-        },
-        Planet {
-            note: Note::Venus,
-            bpm: rotation_around_sun_days::VENUS_ORIGINAL_BPM,
-            orbit_radius: 75,
-            color: sdl2::pixels::Color::RGB(255, 165, 0),
-        },
-        Planet {
-            note: Note::Earth,
-            bpm: rotation_around_sun_days::EARTH_ORIGINAL_BPM,
-            orbit_radius: 100,
-            color: sdl2::pixels::Color::RGB(0, 0, 255),
-        },
-        Planet {
-            note: Note::Mars,
-            bpm: rotation_around_sun_days::MARS_ORIGINAL_BPM,
-            orbit_radius: 125,
-            color: sdl2::pixels::Color::RGB(255, 100, 0),
-        },
-        Planet {
-            note: Note::Jupiter,
-            bpm: rotation_around_sun_days::JUPITER_ORIGINAL_BPM,
-            orbit_radius: 150,
-            color: sdl2::pixels::Color::RGB(218, 165, 200),
-        },
-        Planet {
-            note: Note::Saturn,
-            bpm: rotation_around_sun_days::SATURN_ORIGINAL_BPM,
-            orbit_radius: 175,
-            color: sdl2::pixels::Color::RGB(210, 180, 140),
-        },
-        Planet {
-            note: Note::Uranus,
-            bpm: rotation_around_sun_days::URANUS_ORIGINAL_BPM,
-            orbit_radius: 200,
-            color: sdl2::pixels::Color::RGB(0, 255, 255),
-        },
-        Planet {
-            note: Note::Neptune,
-            bpm: rotation_around_sun_days::NEPTUNE_ORIGINAL_BPM,
-            orbit_radius: 225,
-            color: sdl2::pixels::Color::RGB(0, 0, 139),
-        },
-        Planet {
-            note: Note::Pluto,
-            bpm: rotation_around_sun_days::PLUTO_ORIGINAL_BPM,
-            orbit_radius: 250,
-            color: sdl2::pixels::Color::RGB(128, 128, 128),
-        },
-    ];
-
-    for planet in &planets {
-        let vis_state_clone = Arc::clone(&vis_state);
-        spawn_thread_planet(planet.bpm, planet.note, main_audio.clone(), vis_state_clone);
-    }
+    let vis_state: Arc<Mutex<HashMap<usize, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let drift = Arc::new(Mutex::new(HashMap::new()));
 
-    let start_time = Instant::now();
-    let mut first_time = false;
-    let mut first_time_instant = Instant::now();
+    let end = crate::alignment::predicted_duration(planets);
+    let mut timeline = Timeline::new(start, end.map(crate::alignment::last_note));
+    let mut threads = PlanetThreads::spawn(planets, timeline, &main_audio, &vis_state, &drift);
 
+    // when the planets were found aligned, the window closes LINGER after that
+    let mut completed: Option<Instant> = None;
     'running: loop {
         for event in event_pump.poll_iter() {
-            if let sdl2::event::Event::Quit { .. } = event {
-                break 'running;
-            }
+            let action = match event {
+                Event::Quit { .. } => break 'running,
+                Event::KeyDown {
+                    keycode: Some(key),
+                    keymod,
+                    ..
+                } => controls::action(key, keymod).and_then(|action| view.apply(action)),
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(..),
+                    ..
+                } => {
+                    let resized = Layout::for_window(&canvas);
+                    if resized.dpi != layout.dpi {
+                        font = text::load_font(&ttf, resized.font_size())?;
+                    }
+                    if let Some(pattern) = &mut pattern {
+                        pattern.resize(&texture_creator, canvas.output_size()?)?;
+                    }
+                    layout = resized;
+                    None
+                }
+                _ => None,
+            };
+            let next = match action {
+                Some(Action::TogglePause) => {
+                    let next = timeline.rebase(|clock| clock.set_paused(!clock.paused()));
+                    // notes already ringing hold where they are, like they do in the single threaded player
+                    if next.clock.paused() {
+                        Channel::all().pause();
+                        if let Some(midi) = &midi {
+                            midi.silence();
+                        }
+                    } else {
+                        Channel::all().resume();
+                    }
+                    next
+                }
+                Some(Action::Seek(seconds)) => {
+                    let to = timeline.elapsed() + seconds;
+                    timeline.rebase(|clock| clock.seek(to))
+                }
+                Some(Action::Restart) => timeline.rebase(|clock| clock.seek(0.0)),
+                Some(Action::Speed(factor)) => {
+                    timeline.rebase(|clock| clock.set_speed(clock.speed() * factor))
+                }
+                Some(Action::Volume(step)) => {
+                    let mut mixer = mixer.lock().unwrap();
+                    let master = mixer.master() + step;
+                    mixer.set_master(master);
+                    continue;
+                }
+                Some(Action::Mute(planet)) => {
+                    mixer.lock().unwrap().toggle_mute(planet);
+                    continue;
+                }
+                Some(Action::Solo(planet)) => {
+                    mixer.lock().unwrap().toggle_solo(planet);
+                    continue;
+                }
+                Some(Action::Select(step)) => {
+                    mixer.lock().unwrap().select(step);
+                    continue;
+                }
+                Some(Action::Gain(step)) => {
+                    mixer.lock().unwrap().nudge_gain(step);
+                    continue;
+                }
+                Some(Action::Pan(step)) => {
+                    mixer.lock().unwrap().nudge_pan(step);
+                    continue;
+                }
+                Some(Action::Spatial) => {
+                    let mut mixer = mixer.lock().unwrap();
+                    let spatial = mixer.spatial().next();
+                    mixer.set_spatial(spatial);
+                    continue;
+                }
+                Some(
+                    Action::ToggleOrbits
+                    | Action::ToggleLabels
+                    | Action::ToggleTrails
+                    | Action::ToggleSun
+                    | Action::ToggleSizes
+                    | Action::TogglePattern
+                    | Action::ToggleHud,
+                )
+                | None => continue,
+                Some(Action::ToggleFullscreen) => {
+                    toggle_fullscreen(&mut canvas)?;
+                    continue;
+                }
+            };
+            // the planet threads only know absolute deadlines, so any jump in time means new threads
+            threads.stop();
+            timeline = next;
+            threads = PlanetThreads::spawn(planets, timeline, &main_audio, &vis_state, &drift);
         }
 
         canvas.set_draw_color(sdl2::pixels::Color::RGB(0, 0, 0));
         canvas.clear();
 
-        let center_x = 400;
-        let center_y = 300;
         let current_time = Instant::now();
-        let elapsed = current_time.duration_since(start_time).as_secs_f32();
-
-        let mut flash_count = 0;
-
-        for planet in &planets {
-            let period = 60.0 / planet.bpm;
-            let angle = (elapsed / period) * 2.0 * std::f32::consts::PI;
-            let x = center_x as f32 + planet.orbit_radius as f32 * angle.cos();
-            let y = center_y as f32 + planet.orbit_radius as f32 * angle.sin();
-
-            canvas.set_draw_color(planet.color);
-            let _ = canvas.fill_rect(sdl2::rect::Rect::new(x as i32 - 5, y as i32 - 5, 10, 10));
+        let elapsed = timeline.elapsed();
+        if let Some(osc) = &mut positions {
+            osc.update(elapsed);
+        }
 
-            let flash_duration = Duration::from_millis(200);
-            let mut flash = false;
-            {
-                if let Ok(state) = vis_state.lock() {
-                    if let Some(&last_hit) = state.get(&planet.note) {
-                        if current_time.duration_since(last_hit) < flash_duration {
-                            flash = true;
-                        }
-                    }
+        // keep ringing notes following their planet around
+        {
+            let mixer = mixer.lock().unwrap();
+            if mixer.spatial() != Spatial::Off {
+                for (channel, playing) in channels.lock().unwrap().playing() {
+                    place(channel, mixer.gains_at(playing.planet, elapsed));
                 }
             }
-            if flash {
-                canvas.set_draw_color(sdl2::pixels::Color::RGB(255, 255, 255));
-                let _ = canvas.draw_line(
-                    sdl2::rect::Point::new(center_x, center_y),
-                    sdl2::rect::Point::new(x as i32, y as i32),
-                );
-                flash_count += 1;
+        }
+
+        if let Some(pattern) = &mut pattern {
+            pattern.update(&mut canvas, planets, &layout, elapsed)?;
+            if view.pattern {
+                pattern.draw(&mut canvas)?;
             }
         }
-        if flash_count == 9 && !first_time {
-            // all planet orbited the same time
-            first_time = true;
-            first_time_instant = Instant::now();
-        } else if flash_count == 9 && first_time && first_time_instant.elapsed().as_secs() > 3 {
-            println!("Completed");
-            std::thread::sleep(Duration::from_secs(20));
-            break 'running;
+        let flash_duration = Duration::from_millis(200);
+        let last_hits = vis_state.lock().unwrap().clone();
+        draw_scene(
+            &mut canvas,
+            (&texture_creator, &font),
+            planets,
+            &layout,
+            elapsed,
+            view,
+            |i| {
+                last_hits
+                    .get(&i)
+                    .is_some_and(|&last_hit| current_time.duration_since(last_hit) < flash_duration)
+            },
+        );
+        if view.hud {
+            let (voices, stats) = {
+                let mut channels = channels.lock().unwrap();
+                channels.reap();
+                (channels.playing().count(), channels.stats)
+            };
+            let status = hud::Status {
+                elapsed,
+                end,
+                speed: timeline.clock.speed(),
+                paused: timeline.clock.paused(),
+                voices,
+                stats,
+            };
+            let mixer = mixer.lock().unwrap().clone();
+            hud::draw_hud(
+                &mut canvas,
+                (&texture_creator, &font),
+                planets,
+                &status,
+                &mixer,
+            )?;
+        }
+        if let Some(end) = end.filter(|&end| elapsed >= end) {
+            let since = *completed.get_or_insert_with(|| {
+                println!("Completed");
+                Instant::now()
+            });
+            // the threads are done once the last chord is out, the loop keeps going so the window stays responsive
+            if elapsed > crate::alignment::last_note(end) {
+                std::mem::take(&mut threads).stop();
+            }
+            if since.elapsed() >= crate::LINGER {
+                break 'running;
+            }
+        } else {
+            // seeked back before the end
+            completed = None;
         }
         canvas.present();
         unsafe {
             SDL_Delay(Duration::from_secs_f32(1.0 / 60.0).as_millis() as u32);
         }
     }
-    exit(0);
+
+    threads.stop();
+    drop(main_audio);
+    // the audio thread closes the MIDI port, which waits for this to hang up too
+    drop(midi);
+    let _ = audio_thread.join();
+    let stats = channels.lock().unwrap().stats;
+    println!(
+        "{} notes played, {} stolen, {} dropped, at most {} voices at once",
+        stats.played, stats.stolen, stats.dropped, stats.peak
+    );
+    let drift = drift.lock().unwrap();
+    for (i, planet) in planets.iter().enumerate() {
+        if let Some(drift) = drift.get(&i) {
+            println!(
+                "{}: {} triggers, drift last {:?} mean {:?} max {:?}",
+                planet.name,
+                drift.triggers,
+                drift.last,
+                drift.mean(),
+                drift.max
+            );
+        }
+    }
+    Ok(())
 }
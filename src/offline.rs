@@ -0,0 +1,88 @@
+/// Offline renderer, writes the piece to a WAV file without touching SDL audio
+use std::{fs::File, io::BufWriter, io::Write};
+
+use crate::mixer::{Mixer, Spatial};
+use crate::model::Planet;
+use crate::sequencer::{Sequencer, Track, CHANNELS, SAMPLE_RATE};
+use crate::tuning;
+use crate::voices::Polyphony;
+use crate::wav::{SampleFormat, WavWriter};
+
+const BLOCK_FRAMES: usize = 4096;
+
+/// Decodes every planet's sample into one track per planet
+fn planet_tracks(planets: &[Planet]) -> Result<Vec<Track>, String> {
+    planets
+        .iter()
+        .map(|planet| {
+            let pcm = planet
+                .sample
+                .pcm()
+                .map_err(|e| format!("{}'s sample: {}", planet.name, e))?;
+            // files at other rates get resampled rather than played at the wrong pitch
+            let samples = if pcm.sample_rate == SAMPLE_RATE {
+                pcm.samples
+            } else {
+                tuning::resample(&pcm.samples, pcm.sample_rate as f64 / SAMPLE_RATE as f64)?
+            };
+            Ok(Track {
+                period: planet.period(),
+                samples: samples.into(),
+            })
+        })
+        .collect()
+}
+
+/// A sequencer playing `planets`, with their mixer strips as configured
+pub(crate) fn planet_sequencer(
+    planets: &[Planet],
+    spatial: Spatial,
+    polyphony: Polyphony,
+) -> Result<Sequencer, String> {
+    let mut sequencer = Sequencer::new(planet_tracks(planets)?);
+    *sequencer.mixer_mut() = Mixer::for_planets(planets);
+    sequencer.mixer_mut().set_spatial(spatial);
+    sequencer.set_polyphony(polyphony);
+    Ok(sequencer)
+}
+
+/// One revolution of the slowest planet, used when no duration is asked for
+pub fn default_duration(planets: &[Planet]) -> f64 {
+    planets
+        .iter()
+        .map(|planet| planet.period())
+        .fold(0.0, f64::max)
+}
+
+/// Renders `seconds` of the piece into `path`
+pub fn render_wav(
+    planets: &[Planet],
+    path: &str,
+    seconds: f64,
+    format: SampleFormat,
+    spatial: Spatial,
+    polyphony: Polyphony,
+) -> Result<(), String> {
+    let mut sequencer = planet_sequencer(planets, spatial, polyphony)?;
+    let file = File::create(path).map_err(|e| format!("failed to create {}: {}", path, e))?;
+    let mut writer = WavWriter::new(BufWriter::new(file), SAMPLE_RATE, CHANNELS as u16, format)?;
+
+    let total = (seconds * SAMPLE_RATE as f64).round() as u64;
+    let mut block = vec![0.0; BLOCK_FRAMES * CHANNELS];
+    while sequencer.position() < total {
+        let frames = (total - sequencer.position()).min(BLOCK_FRAMES as u64) as usize;
+        let block = &mut block[..frames * CHANNELS];
+        block.fill(0.0);
+        sequencer.fill(block);
+        writer.write(block)?;
+
+        print!(
+            "\rRendering: {:.1}%",
+            sequencer.position() as f64 / total as f64 * 100.0
+        );
+        std::io::stdout().flush().unwrap();
+    }
+    println!();
+    writer.finish()?;
+    Ok(())
+}
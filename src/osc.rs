@@ -0,0 +1,147 @@
+/// Open Sound Control over UDP, so visuals running in other tools can follow the planets.
+/// Hand rolled since all it takes is a few fixed messages.
+use std::f64::consts::TAU;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::events::NoteEvent;
+use crate::model::Planet;
+use crate::simulation;
+
+/// Real seconds between position updates unless `--osc-interval` says otherwise
+pub const DEFAULT_INTERVAL: f64 = 1.0 / 30.0;
+
+/// An OSC argument
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg {
+    Int(i32),
+    Float(f32),
+    Str(String),
+}
+
+/// `s` null terminated and padded to a multiple of 4 bytes, how OSC wants its strings
+fn write_padded(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(s.as_bytes());
+    out.extend(std::iter::repeat_n(0, 4 - s.len() % 4));
+}
+
+/// An OSC message packet
+pub fn message(address: &str, args: &[Arg]) -> Vec<u8> {
+    let mut packet = Vec::new();
+    write_padded(&mut packet, address);
+    let tags: String = std::iter::once(',')
+        .chain(args.iter().map(|arg| match arg {
+            Arg::Int(_) => 'i',
+            Arg::Float(_) => 'f',
+            Arg::Str(_) => 's',
+        }))
+        .collect();
+    write_padded(&mut packet, &tags);
+    for arg in args {
+        match arg {
+            Arg::Int(i) => packet.extend_from_slice(&i.to_be_bytes()),
+            Arg::Float(f) => packet.extend_from_slice(&f.to_be_bytes()),
+            Arg::Str(s) => write_padded(&mut packet, s),
+        }
+    }
+    packet
+}
+
+/// A planet's name as an OSC address part: lowercase, with anything OSC gives a meaning swapped for `_`
+fn address_part(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            ' ' | '#' | '*' | ',' | '/' | '?' | '[' | ']' | '{' | '}' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Sends `/mandala/planet/<name>` on every trigger and `/mandala/planet/<name>/position` every so often,
+/// both with the planet's angle in radians, its revolution count and the piece time in seconds
+#[derive(Debug, Clone)]
+pub struct OscSender {
+    socket: Arc<UdpSocket>,
+    target: SocketAddr,
+    /// per planet, the trigger address
+    addresses: Vec<String>,
+    periods: Vec<f64>,
+    interval: Duration,
+    last_update: Option<Instant>,
+}
+
+impl OscSender {
+    /// Sends to `target`, e.g. `127.0.0.1:9000`, with position updates every `interval` real seconds
+    pub fn connect(target: &str, interval: f64, planets: &[Planet]) -> Result<Self, String> {
+        let target = target
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addresses| addresses.next())
+            .ok_or_else(|| format!("--osc needs a host:port, got '{}'", target))?;
+        let local = if target.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(local).map_err(|e| format!("can't open OSC socket: {}", e))?;
+        Ok(Self {
+            socket: Arc::new(socket),
+            target,
+            addresses: planets
+                .iter()
+                .map(|planet| format!("/mandala/planet/{}", address_part(&planet.name)))
+                .collect(),
+            periods: planets.iter().map(|planet| planet.period()).collect(),
+            interval: Duration::try_from_secs_f64(interval).map_err(|_| {
+                format!("--osc-interval needs a number of seconds, got {}", interval)
+            })?,
+            last_update: None,
+        })
+    }
+
+    /// Nobody listening isn't an error for UDP, so whatever goes wrong sending is ignored
+    fn send(&self, address: &str, angle: f64, revolution: u64, seconds: f64) {
+        let args = [
+            Arg::Float(angle as f32),
+            Arg::Int(revolution.min(i32::MAX as u64) as i32),
+            Arg::Float(seconds as f32),
+        ];
+        let _ = self.socket.send_to(&message(address, &args), self.target);
+    }
+
+    /// `note`'s trigger message at the time it actually went out. The angle is how far past the start of its
+    /// revolution the planet had got by then, which is next to nothing unless the note was late.
+    pub fn trigger(&self, note: &NoteEvent) {
+        let period = self.periods[note.planet];
+        let late = (note.actual - note.scheduled).max(0.0);
+        self.send(
+            &self.addresses[note.planet],
+            late / period * TAU,
+            note.revolution,
+            note.actual,
+        );
+    }
+
+    /// Every planet's position `elapsed` seconds into the piece, if it's been `interval` since the last ones
+    pub fn update(&mut self, elapsed: f64) {
+        let now = Instant::now();
+        if self
+            .last_update
+            .is_some_and(|last| now.duration_since(last) < self.interval)
+        {
+            return;
+        }
+        self.last_update = Some(now);
+        for (planet, address) in self.addresses.iter().enumerate() {
+            let period = self.periods[planet];
+            self.send(
+                &format!("{}/position", address),
+                simulation::angle(period, elapsed),
+                simulation::revolutions(period, elapsed),
+                elapsed,
+            );
+        }
+    }
+}
@@ -0,0 +1,188 @@
+/// Mandala patterns: chords between pairs of planets drawn every so often and left to pile up,
+/// like the Earth–Venus pentagram
+use std::str::FromStr;
+
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Point;
+use sdl2::render::{BlendMode, Canvas, RenderTarget, Texture, TextureCreator};
+
+use crate::layout::Layout;
+use crate::model::Planet;
+use crate::orbit_position;
+
+/// Piece seconds between chords unless `--pattern-interval` says otherwise
+pub const DEFAULT_INTERVAL: f64 = 0.25;
+/// Most chords drawn at once, after a seek or when running fast enough that a frame covers lots of them
+const MAX_CHORDS: u64 = 2000;
+/// How opaque a chord is with [`Blend::Alpha`]
+const CHORD_ALPHA: u8 = 96;
+
+/// How a new chord combines with what's already there
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Blend {
+    /// chords add up, so where lots of them cross gets brighter
+    #[default]
+    Add,
+    /// chords are drawn see-through over the older ones
+    Alpha,
+}
+
+impl FromStr for Blend {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "add" => Ok(Blend::Add),
+            "alpha" => Ok(Blend::Alpha),
+            _ => Err(format!("unknown blend '{}', expected add or alpha", name)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    /// planet indices to draw chords between
+    pub pairs: Vec<(usize, usize)>,
+    /// piece seconds between chords
+    pub interval: f64,
+    /// how much of the pattern fades away with every new chord, 0 keeps it forever
+    pub fade: f32,
+    pub blend: Blend,
+}
+
+impl Pattern {
+    /// Planet pairs from e.g. `"earth-venus,mars-jupiter"`, names matched case-insensitively
+    pub fn pairs(spec: &str, planets: &[Planet]) -> Result<Vec<(usize, usize)>, String> {
+        let find = |name: &str| {
+            planets
+                .iter()
+                .position(|planet| planet.name.eq_ignore_ascii_case(name.trim()))
+                .ok_or_else(|| format!("no planet called '{}' for the pattern", name.trim()))
+        };
+        spec.split(',')
+            .map(|pair| {
+                let (a, b) = pair.split_once('-').ok_or_else(|| {
+                    format!("pattern pairs look like earth-venus, got '{}'", pair)
+                })?;
+                let (a, b) = (find(a)?, find(b)?);
+                if a == b {
+                    return Err(format!("a pattern pair needs two planets, got '{}'", pair));
+                }
+                Ok((a, b))
+            })
+            .collect()
+    }
+}
+
+/// Halfway between the two planets' colors
+fn mix_colors(a: Color, b: Color) -> Color {
+    let mid = |a: u8, b: u8| ((a as u16 + b as u16) / 2) as u8;
+    Color::RGB(mid(a.r, b.r), mid(a.g, b.g), mid(a.b, b.b))
+}
+
+/// A [`Pattern`] accumulating in its own texture, drawn under the planets
+pub struct PatternLayer<'a> {
+    pattern: Pattern,
+    texture: Texture<'a>,
+    /// index of the next chord to draw, chord `n` being `n * interval` seconds in,
+    /// `None` until the texture's been cleared
+    next: Option<u64>,
+}
+
+impl<'a> PatternLayer<'a> {
+    /// A blank layer `size` pixels big
+    pub fn new<C>(
+        pattern: Pattern,
+        creator: &'a TextureCreator<C>,
+        size: (u32, u32),
+    ) -> Result<Self, String> {
+        let mut texture = creator
+            .create_texture_target(PixelFormatEnum::RGBA8888, size.0, size.1)
+            .map_err(|e| e.to_string())?;
+        texture.set_blend_mode(BlendMode::None);
+        Ok(Self {
+            pattern,
+            texture,
+            next: None,
+        })
+    }
+
+    /// Starts the pattern over on a blank texture `size` pixels big, for when the window's been resized
+    pub fn resize<C>(
+        &mut self,
+        creator: &'a TextureCreator<C>,
+        size: (u32, u32),
+    ) -> Result<(), String> {
+        *self = Self::new(self.pattern.clone(), creator, size)?;
+        Ok(())
+    }
+
+    /// Draws every chord due by `elapsed`. Going back in time, or too far forward, starts over from
+    /// the last [`MAX_CHORDS`] chords, which with any fade at all is what would be showing anyway.
+    pub fn update<T: RenderTarget>(
+        &mut self,
+        canvas: &mut Canvas<T>,
+        planets: &[Planet],
+        layout: &Layout,
+        elapsed: f64,
+    ) -> Result<(), String> {
+        let due = (elapsed / self.pattern.interval).floor() as u64 + 1;
+        let (from, restart) = match self.next {
+            Some(next) if next <= due && due - next <= MAX_CHORDS => (next, false),
+            _ => (due.saturating_sub(MAX_CHORDS), true),
+        };
+        if from == due && !restart {
+            return Ok(());
+        }
+        let pattern = &self.pattern;
+        canvas
+            .with_texture_canvas(&mut self.texture, |canvas| {
+                if restart {
+                    canvas.set_draw_color(Color::BLACK);
+                    canvas.clear();
+                }
+                for n in from..due {
+                    if pattern.fade > 0.0 {
+                        canvas.set_blend_mode(BlendMode::Blend);
+                        canvas.set_draw_color(Color::RGBA(0, 0, 0, (pattern.fade * 255.0) as u8));
+                        let _ = canvas.fill_rect(None);
+                    }
+                    let seconds = n as f64 * pattern.interval;
+                    for &(a, b) in &pattern.pairs {
+                        let (a, b) = (&planets[a], &planets[b]);
+                        let color = mix_colors(a.color, b.color);
+                        match pattern.blend {
+                            Blend::Add => {
+                                canvas.set_blend_mode(BlendMode::Add);
+                                canvas.set_draw_color(color);
+                            }
+                            Blend::Alpha => {
+                                canvas.set_blend_mode(BlendMode::Blend);
+                                canvas.set_draw_color(Color::RGBA(
+                                    color.r,
+                                    color.g,
+                                    color.b,
+                                    CHORD_ALPHA,
+                                ));
+                            }
+                        }
+                        let (ax, ay) = orbit_position(a, layout, seconds);
+                        let (bx, by) = orbit_position(b, layout, seconds);
+                        let _ = canvas.draw_line(
+                            Point::new(ax as i32, ay as i32),
+                            Point::new(bx as i32, by as i32),
+                        );
+                    }
+                }
+                canvas.set_blend_mode(BlendMode::None);
+            })
+            .map_err(|e| e.to_string())?;
+        self.next = Some(due);
+        Ok(())
+    }
+
+    /// Copies the pattern onto `canvas`, meant to go straight after clearing it
+    pub fn draw<T: RenderTarget>(&self, canvas: &mut Canvas<T>) -> Result<(), String> {
+        canvas.copy(&self.texture, None, None)
+    }
+}
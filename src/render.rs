@@ -0,0 +1,118 @@
+/// Video export, runs the orbits on a fixed virtual clock and pipes frames and mixed audio through FFMpeg
+use std::io::Write;
+
+use sdl2::{pixels::PixelFormatEnum, surface::Surface};
+
+use crate::cli::Settings;
+use crate::ffmpeg::{self, AudioRecorder, VideoRecorder};
+use crate::layout::Layout;
+use crate::model::Planet;
+use crate::pattern::PatternLayer;
+use crate::sequencer::{CHANNELS, SAMPLE_RATE};
+use crate::{draw_scene, flashing, offline, text};
+
+pub const WIDTH: u32 = 800;
+pub const HEIGHT: u32 = 600;
+pub const FPS: u32 = 60;
+
+/// Renders `seconds` of the piece into `out` (anything FFMpeg can mux h264 + aac into)
+pub fn render_video(
+    planets: &[Planet],
+    out: &str,
+    seconds: f64,
+    settings: Settings,
+) -> Result<(), String> {
+    let video_path = format!("{}.video.mp4", out);
+    let audio_path = format!("{}.audio.m4a", out);
+    let result = record(planets, (&video_path, &audio_path), seconds, settings)
+        .and_then(|()| ffmpeg::mux(&video_path, &audio_path, out));
+    // worked or not, the separate streams are no use any more
+    let _ = std::fs::remove_file(&video_path);
+    let _ = std::fs::remove_file(&audio_path);
+    result
+}
+
+/// Encodes the video and audio of `seconds` of the piece into two separate files, for [`ffmpeg::mux`] to put together
+fn record(
+    planets: &[Planet],
+    (video_path, audio_path): (&str, &str),
+    seconds: f64,
+    settings: Settings,
+) -> Result<(), String> {
+    // the export always starts at the beginning at normal speed, so the clock isn't used
+    let Settings {
+        spatial,
+        polyphony,
+        view,
+        pattern,
+        ..
+    } = settings;
+    let _sdl = sdl2::init()?;
+    let mut canvas = Surface::new(WIDTH, HEIGHT, PixelFormatEnum::RGB24)?
+        .into_canvas()
+        .map_err(|e| e.to_string())?;
+    let texture_creator = canvas.texture_creator();
+    let ttf = sdl2::ttf::init().map_err(|e| e.to_string())?;
+    let font = text::load_font(&ttf, text::FONT_SIZE)?;
+    let mut pattern = pattern
+        .map(|pattern| PatternLayer::new(pattern, &texture_creator, (WIDTH, HEIGHT)))
+        .transpose()?;
+
+    let mut sequencer = offline::planet_sequencer(planets, spatial, polyphony)?;
+    let layout = Layout::new((WIDTH, HEIGHT), 1.0);
+
+    let mut video = VideoRecorder::new(video_path, WIDTH, HEIGHT, FPS)?;
+    let mut audio = AudioRecorder::new(audio_path, SAMPLE_RATE, CHANNELS as u32)?;
+
+    let total_frames = (seconds * FPS as f64).round() as u64;
+    let mut block = Vec::new();
+    for frame in 0..total_frames {
+        let elapsed = frame as f64 / FPS as f64;
+
+        canvas.set_draw_color(sdl2::pixels::Color::RGB(0, 0, 0));
+        canvas.clear();
+        if let Some(pattern) = &mut pattern {
+            pattern.update(&mut canvas, planets, &layout, elapsed)?;
+            if view.pattern {
+                pattern.draw(&mut canvas)?;
+            }
+        }
+        draw_scene(
+            &mut canvas,
+            (&texture_creator, &font),
+            planets,
+            &layout,
+            elapsed,
+            view,
+            |i| flashing(&planets[i], elapsed),
+        );
+        canvas.present();
+        video.process_frame(canvas.read_pixels(None, PixelFormatEnum::RGB24)?)?;
+
+        // audio up to the end of this frame, rounded per frame so it never drifts from the video
+        let audio_end = ((frame + 1) * SAMPLE_RATE as u64) / FPS as u64;
+        let frames = (audio_end - sequencer.position()) as usize;
+        block.clear();
+        block.resize(frames * CHANNELS, 0.0);
+        sequencer.fill(&mut block);
+        audio.process_audio(
+            block
+                .iter()
+                .flat_map(|s| ((s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes())
+                .collect(),
+        )?;
+
+        print!(
+            "\rRendering frame {}/{} ({:.1}%)",
+            frame + 1,
+            total_frames,
+            (frame + 1) as f64 / total_frames as f64 * 100.0
+        );
+        std::io::stdout().flush().unwrap();
+    }
+    println!();
+
+    video.done();
+    audio.done();
+    Ok(())
+}
@@ -0,0 +1,329 @@
+/// Sample-accurate note sequencer, every trigger lands on the exact frame it is due
+use std::sync::Arc;
+
+use crate::clock::Clock;
+use crate::events::NoteEvent;
+use crate::mixer::Mixer;
+use crate::simulation;
+use crate::voices::{Admit, Loudness, Polyphony, Ringing, VoiceStats};
+
+/// Output rate used everywhere, same as what the mixer is opened with
+pub const SAMPLE_RATE: u32 = 44100;
+/// Interleaved output channels
+pub const CHANNELS: usize = 2;
+/// Matches `Channel::all().set_volume(100)` out of SDL_mixer's 128
+pub const MASTER_GAIN: f32 = 100.0 / 128.0;
+/// How long a stolen voice takes to fade out, short enough to make room but long enough not to click
+const STEAL_FADE: u32 = 256;
+
+/// One repeating voice: a mono sample retriggered once per `period` seconds
+#[derive(Clone)]
+pub struct Track {
+    pub period: f64,
+    pub samples: Arc<[f32]>,
+}
+
+struct Voice {
+    track: usize,
+    pos: usize,
+    /// frames left of the fade out once it's been stolen
+    fade: Option<u32>,
+    /// voice number, below the polyphony limit like a mixer channel
+    slot: usize,
+}
+
+pub struct Sequencer {
+    tracks: Vec<Track>,
+    /// revolution index of the next trigger, per track
+    next: Vec<u64>,
+    voices: Vec<Voice>,
+    polyphony: Polyphony,
+    stats: VoiceStats,
+    /// per track, for stealing the quietest voice
+    loudness: Vec<Loudness>,
+    /// frames rendered so far
+    position: u64,
+    /// where in the piece those frames got to
+    clock: Clock,
+    mixer: Mixer,
+    /// every trigger since the last [`Sequencer::take_events`], once [`Sequencer::log_events`] has been called
+    events: Option<Vec<NoteEvent>>,
+    /// piece frame after which nothing more is triggered
+    end: Option<u64>,
+}
+
+impl Sequencer {
+    pub fn new(tracks: Vec<Track>) -> Self {
+        Self {
+            next: vec![0; tracks.len()],
+            voices: Vec::new(),
+            polyphony: Polyphony::default(),
+            stats: VoiceStats::default(),
+            loudness: tracks
+                .iter()
+                .map(|track| Loudness::new(&track.samples))
+                .collect(),
+            position: 0,
+            clock: Clock::default(),
+            mixer: Mixer::new(vec![Default::default(); tracks.len()]),
+            events: None,
+            end: None,
+            tracks,
+        }
+    }
+
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    /// Notes currently ringing
+    pub fn voices(&self) -> usize {
+        self.voices.len()
+    }
+
+    pub fn polyphony(&self) -> Polyphony {
+        self.polyphony
+    }
+
+    /// Applies to notes triggered from now on, voices already ringing past the new limits are left to finish
+    pub fn set_polyphony(&mut self, polyphony: Polyphony) {
+        self.polyphony = polyphony;
+    }
+
+    /// Notes played, stolen and dropped so far
+    pub fn stats(&self) -> VoiceStats {
+        self.stats
+    }
+
+    /// Starts keeping a [`NoteEvent`] for every trigger, for the event log
+    pub fn log_events(&mut self) {
+        self.events.get_or_insert_with(Vec::new);
+    }
+
+    /// The triggers kept since last time, none unless [`Sequencer::log_events`] was called
+    pub fn take_events(&mut self) -> Vec<NoteEvent> {
+        self.events.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Frames rendered since the start
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Where in the piece playback is, which isn't [`Sequencer::position`] once it's been seeked, sped up or paused
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Jumps to `seconds` into the piece. Ringing notes are cut and every track picks up at its next trigger after that point.
+    pub fn seek(&mut self, seconds: f64) {
+        self.clock.seek(seconds);
+        self.voices.clear();
+        let position = self.clock.position();
+        for track in 0..self.tracks.len() {
+            let mut n =
+                simulation::next_revolution(self.tracks[track].period, self.clock.seconds());
+            // deadlines are rounded to whole frames, nudge `n` onto the first one at or after `position`
+            while n > 0 && self.deadline(track, n - 1) as f64 >= position {
+                n -= 1;
+            }
+            while (self.deadline(track, n) as f64) < position {
+                n += 1;
+            }
+            self.next[track] = n;
+        }
+    }
+
+    /// Notes fire `speed` times as often, the samples themselves still play at their own pitch
+    pub fn set_speed(&mut self, speed: f64) {
+        self.clock.set_speed(speed);
+    }
+
+    /// Paused playback renders silence and keeps its place, ringing notes included
+    pub fn set_paused(&mut self, paused: bool) {
+        self.clock.set_paused(paused);
+    }
+
+    /// No notes are triggered after `seconds` into the piece, the ones already ringing still play out
+    pub fn set_end(&mut self, seconds: Option<f64>) {
+        self.end = seconds.map(|seconds| (seconds * SAMPLE_RATE as f64).floor() as u64);
+    }
+
+    /// Frame on which revolution `n` of `track` starts, the [`simulation::trigger_seconds`] rounded to a whole frame
+    fn deadline(&self, track: usize, n: u64) -> u64 {
+        (simulation::trigger_seconds(self.tracks[track].period, n) * SAMPLE_RATE as f64).round()
+            as u64
+    }
+
+    /// Gain, pan, mute and solo per track. Muted tracks keep their place and ringing notes, they're just not mixed in.
+    pub fn mixer(&self) -> &Mixer {
+        &self.mixer
+    }
+
+    pub fn mixer_mut(&mut self) -> &mut Mixer {
+        &mut self.mixer
+    }
+
+    /// Renders the next `out.len() / CHANNELS` frames of interleaved audio, mixing on top of what's in `out`
+    pub fn fill(&mut self, out: &mut [f32]) {
+        let frames = (out.len() / CHANNELS) as u64;
+        self.position += frames;
+        if self.clock.paused() {
+            return;
+        }
+
+        // gains at both ends of the block, ramped in between so spatial panning glides along with the orbits
+        let mut end = self.clock;
+        end.advance(frames);
+        let gains: Vec<Ramp> = (0..self.tracks.len())
+            .map(|track| {
+                let from = self.mixer.gains_at(track, self.clock.seconds());
+                let to = self.mixer.gains_at(track, end.seconds());
+                Ramp::new(from, to, frames)
+            })
+            .collect();
+        for voice in &mut self.voices {
+            let samples = &self.tracks[voice.track].samples;
+            mix(samples, voice, out, 0, gains[voice.track]);
+        }
+        for (track, &gain) in gains.iter().enumerate() {
+            loop {
+                let deadline = self.deadline(track, self.next[track]);
+                if self.end.is_some_and(|end| deadline > end) {
+                    break;
+                }
+                let Some(start) = self.clock.offset_of(deadline, frames) else {
+                    break;
+                };
+                let revolution = self.next[track];
+                self.next[track] += 1;
+                let slot = self
+                    .admit(track, start as usize, &gains)
+                    .then(|| self.free_slot());
+                if let Some(events) = &mut self.events {
+                    events.push(NoteEvent {
+                        planet: track,
+                        revolution,
+                        scheduled: simulation::trigger_seconds(
+                            self.tracks[track].period,
+                            revolution,
+                        ),
+                        actual: self.clock.seconds()
+                            + start as f64 * self.clock.rate() / SAMPLE_RATE as f64,
+                        voice: slot,
+                        speed: self.clock.speed(),
+                    });
+                }
+                let Some(slot) = slot else {
+                    continue;
+                };
+                let mut voice = Voice {
+                    track,
+                    pos: 0,
+                    fade: None,
+                    slot,
+                };
+                let samples = &self.tracks[track].samples;
+                mix(samples, &mut voice, out, start as usize, gain);
+                self.voices.push(voice);
+            }
+        }
+
+        let tracks = &self.tracks;
+        self.voices
+            .retain(|v| v.pos < tracks[v.track].samples.len() && v.fade != Some(0));
+        self.clock = end;
+    }
+
+    /// Lowest voice number no ringing voice has, voices fading out have given theirs up
+    fn free_slot(&self) -> usize {
+        (0..)
+            .find(|&slot| {
+                !self
+                    .voices
+                    .iter()
+                    .any(|voice| voice.fade.is_none() && voice.slot == slot)
+            })
+            .unwrap_or_default()
+    }
+
+    /// Makes room for a note on `track` starting `start` frames into the block, fading out a stolen voice.
+    /// False if the note has to be dropped.
+    fn admit(&mut self, track: usize, start: usize, gains: &[Ramp]) -> bool {
+        // voices already fading out have given up their place
+        let (indices, ringing): (Vec<usize>, Vec<Ringing>) = self
+            .voices
+            .iter()
+            .enumerate()
+            .filter(|(_, voice)| voice.fade.is_none())
+            .map(|(i, voice)| {
+                let (left, right) = gains[voice.track].at(start);
+                let ringing = Ringing {
+                    planet: voice.track,
+                    age: voice.pos as f64 / SAMPLE_RATE as f64,
+                    level: left.max(right) * self.loudness[voice.track].at(voice.pos),
+                };
+                (i, ringing)
+            })
+            .unzip();
+        let admit = self.polyphony.admit(track, &ringing);
+        if let Admit::Steal(stolen) = admit {
+            self.voices[indices[stolen]].fade = Some(STEAL_FADE);
+        }
+        let ringing = match admit {
+            Admit::Play => ringing.len() + 1,
+            Admit::Steal(_) | Admit::Drop => ringing.len(),
+        };
+        self.stats.record(admit, ringing);
+        admit != Admit::Drop
+    }
+}
+
+/// Left and right gain moving linearly across a block
+#[derive(Clone, Copy)]
+struct Ramp {
+    from: (f32, f32),
+    step: (f32, f32),
+}
+
+impl Ramp {
+    fn new(from: (f32, f32), to: (f32, f32), frames: u64) -> Self {
+        let frames = frames.max(1) as f32;
+        Self {
+            from: (from.0 * MASTER_GAIN, from.1 * MASTER_GAIN),
+            step: (
+                (to.0 - from.0) * MASTER_GAIN / frames,
+                (to.1 - from.1) * MASTER_GAIN / frames,
+            ),
+        }
+    }
+
+    fn at(&self, frame: usize) -> (f32, f32) {
+        let frame = frame as f32;
+        (
+            self.from.0 + self.step.0 * frame,
+            self.from.1 + self.step.1 * frame,
+        )
+    }
+}
+
+fn mix(samples: &[f32], voice: &mut Voice, out: &mut [f32], start_frame: usize, gain: Ramp) {
+    let frames = out[start_frame * CHANNELS..].chunks_exact_mut(CHANNELS);
+    for (i, frame) in frames.enumerate() {
+        let Some(&s) = samples.get(voice.pos) else {
+            break;
+        };
+        let fade = match voice.fade {
+            Some(0) => break,
+            Some(left) => {
+                voice.fade = Some(left - 1);
+                left as f32 / STEAL_FADE as f32
+            }
+            None => 1.0,
+        };
+        let (left, right) = gain.at(start_frame + i);
+        frame[0] += s * left * fade;
+        frame[1] += s * right * fade;
+        voice.pos += 1;
+    }
+}
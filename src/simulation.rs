@@ -0,0 +1,134 @@
+/// Headless model of the piece: where every planet is and when its notes fall, with no SDL in sight,
+/// so the timing can be tested without a window, a mixer or a real clock
+use std::f64::consts::TAU;
+
+use crate::model::Planet;
+
+/// Piece time of `revolution`'s trigger, always measured from the start so errors never pile up
+pub fn trigger_seconds(period: f64, revolution: u64) -> f64 {
+    revolution as f64 * period
+}
+
+/// Angle of a planet with `period` after `seconds`, in radians from 0 up to a full turn
+pub fn angle(period: f64, seconds: f64) -> f64 {
+    (seconds / period).fract() * TAU
+}
+
+/// Revolutions completed after `seconds`
+pub fn revolutions(period: f64, seconds: f64) -> u64 {
+    (seconds / period).floor() as u64
+}
+
+/// The first revolution whose trigger is at or after `seconds`
+pub fn next_revolution(period: f64, seconds: f64) -> u64 {
+    (seconds / period).ceil() as u64
+}
+
+/// Seconds since the last trigger
+pub fn since_last(period: f64, seconds: f64) -> f64 {
+    seconds % period
+}
+
+/// Seconds until the next trigger, 0 right on one
+pub fn until_next(period: f64, seconds: f64) -> f64 {
+    match since_last(period, seconds) {
+        0.0 => 0.0,
+        since => period - since,
+    }
+}
+
+/// Distance from `seconds` to the closest trigger, before or after
+pub fn off_beat(period: f64, seconds: f64) -> f64 {
+    (seconds - (seconds / period).round() * period).abs()
+}
+
+/// A planet's note
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Trigger {
+    pub planet: usize,
+    /// 0 is the note right at the start
+    pub revolution: u64,
+    /// piece time it falls on
+    pub seconds: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    periods: Vec<f64>,
+    elapsed: f64,
+    /// per planet, the revolution whose trigger comes next
+    next: Vec<u64>,
+}
+
+impl Simulation {
+    /// Every planet at its starting point, about to play its first note
+    pub fn new(periods: Vec<f64>) -> Self {
+        Self {
+            next: vec![0; periods.len()],
+            elapsed: 0.0,
+            periods,
+        }
+    }
+
+    pub fn for_planets(planets: &[Planet]) -> Self {
+        Self::new(planets.iter().map(|planet| planet.period()).collect())
+    }
+
+    pub fn periods(&self) -> &[f64] {
+        &self.periods
+    }
+
+    /// Seconds into the piece
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    /// Jumps to `seconds`, a trigger right on it still counts as coming up
+    pub fn seek(&mut self, seconds: f64) {
+        self.elapsed = seconds.max(0.0);
+        for (next, &period) in self.next.iter_mut().zip(&self.periods) {
+            *next = next_revolution(period, self.elapsed);
+        }
+    }
+
+    /// Moves `dt` seconds on and returns every trigger passed, in the order they fall.
+    /// One right at the start of the step counts, one right at the end is left for the next step.
+    pub fn advance(&mut self, dt: f64) -> Vec<Trigger> {
+        let end = self.elapsed + dt.max(0.0);
+        let mut triggers = Vec::new();
+        for (planet, (next, &period)) in self.next.iter_mut().zip(&self.periods).enumerate() {
+            while trigger_seconds(period, *next) < end {
+                triggers.push(Trigger {
+                    planet,
+                    revolution: *next,
+                    seconds: trigger_seconds(period, *next),
+                });
+                *next += 1;
+            }
+        }
+        triggers.sort_by(|a, b| a.seconds.total_cmp(&b.seconds));
+        self.elapsed = end;
+        triggers
+    }
+
+    /// `planet`'s angle in radians, see [`angle`]
+    pub fn angle(&self, planet: usize) -> f64 {
+        angle(self.periods[planet], self.elapsed)
+    }
+
+    pub fn revolutions(&self, planet: usize) -> u64 {
+        revolutions(self.periods[planet], self.elapsed)
+    }
+
+    /// Seconds until `planet`'s next note
+    pub fn until_next(&self, planet: usize) -> f64 {
+        until_next(self.periods[planet], self.elapsed)
+    }
+
+    /// Whether every planet is within `tolerance` seconds of one of its triggers
+    pub fn aligned(&self, tolerance: f64) -> bool {
+        self.periods
+            .iter()
+            .all(|&period| off_beat(period, self.elapsed) <= tolerance)
+    }
+}
@@ -0,0 +1,113 @@
+/// Additive synth, so a planet's note can be generated from a fundamental and a harmonic number
+/// instead of needing a WAV for every pitch
+use std::f64::consts::TAU;
+
+/// B1, what the embedded `harmonic_series_N` notes are the harmonics of
+pub const FUNDAMENTAL: f32 = 61.735;
+/// Peak level of a rendered note, about where the embedded WAVs peak
+const LEVEL: f32 = 0.85;
+/// Longest a synth note can ring in seconds, every note is rendered up front and this is already 30 MB of them
+pub const MAX_LENGTH: f32 = 180.0;
+
+/// Attack/decay/sustain/release, times in seconds and `sustain` as a level
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Envelope {
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32,
+    /// how long the note is held before the release starts
+    pub hold: f32,
+}
+
+impl Default for Envelope {
+    /// A soft bell-ish ping lasting 3 seconds like the embedded notes
+    fn default() -> Self {
+        Self {
+            attack: 0.01,
+            decay: 0.4,
+            sustain: 0.4,
+            release: 2.0,
+            hold: 1.0,
+        }
+    }
+}
+
+impl Envelope {
+    /// Level `t` seconds after note on, ramping linearly between the stages
+    pub fn level(&self, t: f32) -> f32 {
+        let held = |t: f32| {
+            if t < self.attack {
+                t / self.attack
+            } else if t < self.attack + self.decay {
+                1.0 - (1.0 - self.sustain) * (t - self.attack) / self.decay
+            } else {
+                self.sustain
+            }
+        };
+        if t < self.hold {
+            held(t)
+        } else {
+            held(self.hold) * (1.0 - (t - self.hold) / self.release).max(0.0)
+        }
+    }
+
+    /// Note on to the end of the release
+    pub fn length(&self) -> f32 {
+        self.hold + self.release
+    }
+}
+
+/// One planet's synthesized note
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tone {
+    pub fundamental: f32,
+    /// which harmonic of `fundamental` to play, 1 is the fundamental itself
+    pub harmonic: u32,
+    /// amplitude of each partial of the note, `[1.0]` is a plain sine
+    pub partials: Vec<f32>,
+    pub envelope: Envelope,
+}
+
+impl Tone {
+    /// Harmonic `n` of [`FUNDAMENTAL`] with the default timbre, a stand in for the embedded note of the same number
+    pub fn harmonic(n: u32) -> Self {
+        Self {
+            fundamental: FUNDAMENTAL,
+            harmonic: n,
+            partials: vec![1.0, 0.5, 0.25, 0.125],
+            envelope: Envelope::default(),
+        }
+    }
+
+    pub fn frequency(&self) -> f32 {
+        self.fundamental * self.harmonic as f32
+    }
+
+    /// Renders the whole note as mono samples, partials above Nyquist are left out
+    pub fn render(&self, sample_rate: u32) -> Vec<f32> {
+        let frequency = self.frequency() as f64;
+        let nyquist = sample_rate as f64 / 2.0;
+        let partials: Vec<(f64, f32)> = self
+            .partials
+            .iter()
+            .enumerate()
+            .map(|(k, &amplitude)| ((k + 1) as f64 * frequency, amplitude))
+            .filter(|&(f, _)| f < nyquist)
+            .collect();
+        let peak = partials.iter().map(|(_, a)| a.abs()).sum::<f32>();
+        let scale = if peak > 0.0 { LEVEL / peak } else { 0.0 };
+
+        let total = (self.envelope.length() * sample_rate as f32).ceil() as usize;
+        (0..total)
+            .map(|i| {
+                let t = i as f64 / sample_rate as f64;
+                let wave: f32 = partials
+                    .iter()
+                    .map(|&(f, a)| a * (TAU * f * t).sin() as f32)
+                    .sum();
+                wave * scale * self.envelope.level(t as f32)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,234 @@
+/// Planetary system files (TOML or JSON), so new pieces can be composed without recompiling
+use sdl2::pixels::Color;
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::formats;
+use crate::layout;
+use crate::mixer::Strip;
+use crate::model::{Planet, Sample};
+use crate::synth::{self, Tone};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SystemFile {
+    /// defaults for every planet using the synth
+    synth: Option<SynthEntry>,
+    planets: Vec<Body>,
+}
+
+/// One `[[planets]]` entry
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Body {
+    name: String,
+    /// notes per minute, or...
+    bpm: Option<f32>,
+    /// ...seconds per revolution
+    period: Option<f64>,
+    /// one of the embedded notes by planet name, or...
+    note: Option<String>,
+    /// ...a WAV, OGG, FLAC or MP3 file, relative to the system file, or...
+    sample: Option<String>,
+    /// ...this harmonic of the synth's fundamental
+    harmonic: Option<u32>,
+    /// synth settings for just this planet
+    synth: Option<SynthEntry>,
+    color: ColorEntry,
+    /// orbit radius in pixels of the original 800×600 window, or...
+    radius: Option<f32>,
+    /// ...normalized, 1.0 reaching the edge of the window's shorter side
+    orbit: Option<f32>,
+    /// in km, only used for drawing planets to scale
+    diameter: Option<f32>,
+    /// mixer level, 1.0 by default
+    gain: Option<f32>,
+    /// -1.0 (left) to 1.0 (right), centered by default
+    pan: Option<f32>,
+}
+
+/// `[synth]` or a planet's `synth = { ... }`, anything left out falls back to the file's `[synth]` and then the built-in sound
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct SynthEntry {
+    fundamental: Option<f32>,
+    partials: Option<Vec<f32>>,
+    attack: Option<f32>,
+    decay: Option<f32>,
+    sustain: Option<f32>,
+    hold: Option<f32>,
+    release: Option<f32>,
+}
+
+impl SynthEntry {
+    fn apply(&self, tone: &mut Tone) {
+        let envelope = &mut tone.envelope;
+        tone.fundamental = self.fundamental.unwrap_or(tone.fundamental);
+        if let Some(partials) = &self.partials {
+            tone.partials = partials.clone();
+        }
+        envelope.attack = self.attack.unwrap_or(envelope.attack);
+        envelope.decay = self.decay.unwrap_or(envelope.decay);
+        envelope.sustain = self.sustain.unwrap_or(envelope.sustain);
+        envelope.hold = self.hold.unwrap_or(envelope.hold);
+        envelope.release = self.release.unwrap_or(envelope.release);
+    }
+}
+
+/// Harmonic `harmonic` with the file's defaults and then the planet's own settings applied
+fn tone(harmonic: u32, defaults: &SynthEntry, own: &SynthEntry) -> Result<Tone, String> {
+    let mut tone = Tone::harmonic(harmonic);
+    defaults.apply(&mut tone);
+    own.apply(&mut tone);
+    let envelope = tone.envelope;
+    if harmonic == 0 {
+        return Err("harmonics start at 1".to_string());
+    }
+    if !(tone.fundamental.is_finite() && tone.fundamental > 0.0) {
+        return Err(format!(
+            "fundamental must be positive, got {} Hz",
+            tone.fundamental
+        ));
+    }
+    if tone.partials.is_empty() || tone.partials.iter().any(|a| !a.is_finite()) {
+        return Err("partials must be a non-empty list of amplitudes".to_string());
+    }
+    let times = [
+        envelope.attack,
+        envelope.decay,
+        envelope.hold,
+        envelope.release,
+    ];
+    if times.iter().any(|t| !(t.is_finite() && *t >= 0.0)) || envelope.length() <= 0.0 {
+        return Err("envelope times must not be negative, and the note can't be empty".to_string());
+    }
+    if envelope.length() > synth::MAX_LENGTH {
+        return Err(format!(
+            "hold and release add up to {}s, a synth note can last {}s at most",
+            envelope.length(),
+            synth::MAX_LENGTH
+        ));
+    }
+    if !(0.0..=1.0).contains(&envelope.sustain) {
+        return Err(format!(
+            "sustain must be between 0 and 1, got {}",
+            envelope.sustain
+        ));
+    }
+    Ok(tone)
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorEntry {
+    Rgb([u8; 3]),
+    /// `"#rrggbb"`
+    Hex(String),
+}
+
+impl ColorEntry {
+    fn to_color(&self) -> Result<Color, String> {
+        match self {
+            ColorEntry::Rgb([r, g, b]) => Ok(Color::RGB(*r, *g, *b)),
+            ColorEntry::Hex(hex) => {
+                let digits = hex.trim_start_matches('#');
+                match u32::from_str_radix(digits, 16) {
+                    Ok(rgb) if digits.len() == 6 => {
+                        Ok(Color::RGB((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+                    }
+                    _ => Err(format!("'{}' is not a #rrggbb color", hex)),
+                }
+            }
+        }
+    }
+}
+
+impl Body {
+    fn into_planet(self, base_dir: &Path, synth: &SynthEntry) -> Result<Planet, String> {
+        let bpm = match (self.bpm, self.period) {
+            (Some(bpm), None) => bpm,
+            (None, Some(period)) => (60.0 / period) as f32,
+            _ => return Err("needs exactly one of `bpm` or `period`".to_string()),
+        };
+        if !(bpm.is_finite() && bpm > 0.0) {
+            return Err(format!("tempo must be positive, got {} bpm", bpm));
+        }
+        let orbit_radius = match (self.radius, self.orbit) {
+            (Some(pixels), None) => layout::from_pixels(pixels),
+            (None, Some(orbit)) => orbit,
+            _ => return Err("needs exactly one of `radius` or `orbit`".to_string()),
+        };
+        if !(orbit_radius.is_finite() && orbit_radius > 0.0) {
+            return Err(format!("orbit must be positive, got {}", orbit_radius));
+        }
+        if let Some(diameter) = self.diameter.filter(|&d| !(d.is_finite() && d > 0.0)) {
+            return Err(format!("diameter must be positive, got {}", diameter));
+        }
+        let strip = Strip {
+            gain: self.gain.unwrap_or(1.0),
+            pan: self.pan.unwrap_or(0.0),
+            muted: false,
+        };
+        if !(strip.gain.is_finite() && strip.gain >= 0.0) {
+            return Err(format!("gain must not be negative, got {}", strip.gain));
+        }
+        if !(-1.0..=1.0).contains(&strip.pan) {
+            return Err(format!("pan must be between -1 and 1, got {}", strip.pan));
+        }
+        if self.synth.is_some() && self.harmonic.is_none() {
+            return Err("`synth` settings only apply to planets with a `harmonic`".to_string());
+        }
+        let sample = match (self.note, self.sample, self.harmonic) {
+            (Some(note), None, None) => Sample::Embedded(note.parse()?),
+            (None, Some(path), None) => {
+                let path = base_dir.join(path);
+                formats::check(&path)?;
+                Sample::File(path)
+            }
+            (None, None, Some(harmonic)) => {
+                Sample::Synth(tone(harmonic, synth, &self.synth.unwrap_or_default())?)
+            }
+            _ => return Err("needs exactly one of `note`, `sample` or `harmonic`".to_string()),
+        };
+        Ok(Planet {
+            name: self.name,
+            sample,
+            bpm,
+            orbit_radius,
+            color: self.color.to_color()?,
+            diameter: self.diameter,
+            strip,
+        })
+    }
+}
+
+/// Parses a system from TOML or JSON text, resolving sample paths against `base_dir`
+pub fn parse_system(text: &str, json: bool, base_dir: &Path) -> Result<Vec<Planet>, String> {
+    let file: SystemFile = if json {
+        serde_json::from_str(text).map_err(|e| e.to_string())?
+    } else {
+        toml::from_str(text).map_err(|e| e.to_string())?
+    };
+    if file.planets.is_empty() {
+        return Err("a system needs at least one planet".to_string());
+    }
+    let synth = file.synth.unwrap_or_default();
+    file.planets
+        .into_iter()
+        .enumerate()
+        .map(|(i, body)| {
+            let name = body.name.clone();
+            body.into_planet(base_dir, &synth)
+                .map_err(|e| format!("planet #{} ('{}'): {}", i + 1, name, e))
+        })
+        .collect()
+}
+
+/// Loads a system file, JSON if it ends in `.json` and TOML otherwise
+pub fn load_system(path: &Path) -> Result<Vec<Planet>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    let json = path.extension().is_some_and(|ext| ext == "json");
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    parse_system(&text, json, base_dir).map_err(|e| format!("{}: {}", path.display(), e))
+}
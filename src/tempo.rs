@@ -0,0 +1,47 @@
+/// Planet tempos worked out from orbital periods, for a chosen base tempo or piece length
+use crate::model::rotation_around_sun_days::{EARTH_FACTOR, EARTH_ORIGINAL_BPM};
+
+/// How orbital periods get turned into tempos
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tempo {
+    /// An orbit of `days` plays at `bpm`, everything else scales from it
+    Reference { days: f32, bpm: f32 },
+    /// The slowest orbit makes exactly one revolution in this many seconds
+    Length(f32),
+}
+
+/// Earth at 30 bpm, what the original tempos were derived from
+pub const ORIGINAL_TEMPO: Tempo = Tempo::Reference {
+    days: EARTH_FACTOR,
+    bpm: EARTH_ORIGINAL_BPM,
+};
+
+/// Tempo of an orbit lasting `period_days` when an orbit of `reference_days` plays at `reference_bpm`
+pub fn bpm_from_period(period_days: f32, reference_days: f32, reference_bpm: f32) -> f32 {
+    reference_bpm * reference_days / period_days
+}
+
+impl Tempo {
+    /// Earth at `bpm`, the rest of the solar system follows
+    pub fn earth(bpm: f32) -> Self {
+        Tempo::Reference {
+            days: EARTH_FACTOR,
+            bpm,
+        }
+    }
+
+    /// One tempo per orbital period, in the same order
+    pub fn bpms(&self, periods_days: &[f32]) -> Vec<f32> {
+        let (days, bpm) = match *self {
+            Tempo::Reference { days, bpm } => (days, bpm),
+            Tempo::Length(seconds) => (
+                periods_days.iter().copied().fold(0.0, f32::max),
+                60.0 / seconds,
+            ),
+        };
+        periods_days
+            .iter()
+            .map(|&period| bpm_from_period(period, days, bpm))
+            .collect()
+    }
+}
@@ -0,0 +1,42 @@
+/// Text drawing with DejaVu Sans Mono baked into the binary, so there's no font file to ship next to it
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, RenderTarget, TextureCreator};
+use sdl2::rwops::RWops;
+use sdl2::ttf::{Font, Sdl2TtfContext};
+
+const FONT: &[u8] = include_bytes!("../fonts/DejaVuSansMono.ttf");
+/// Point size of all the text, before HiDPI scaling
+pub const FONT_SIZE: u16 = 12;
+
+/// Opens the embedded font at `size` points
+pub fn load_font(ttf: &Sdl2TtfContext, size: u16) -> Result<Font<'_, 'static>, String> {
+    ttf.load_font_from_rwops(RWops::from_bytes(FONT)?, size)
+}
+
+/// Draws `text` with its top left corner at `(x, y)`
+pub fn draw_text<T: RenderTarget, C>(
+    canvas: &mut Canvas<T>,
+    creator: &TextureCreator<C>,
+    font: &Font,
+    text: &str,
+    (x, y): (i32, i32),
+    color: Color,
+) -> Result<(), String> {
+    // SDL_ttf refuses to render empty strings
+    if text.is_empty() {
+        return Ok(());
+    }
+    let surface = font
+        .render(text)
+        .blended(color)
+        .map_err(|e| e.to_string())?;
+    let texture = creator
+        .create_texture_from_surface(&surface)
+        .map_err(|e| e.to_string())?;
+    canvas.copy(
+        &texture,
+        None,
+        Rect::new(x, y, surface.width(), surface.height()),
+    )
+}
@@ -0,0 +1,104 @@
+/// Tuning systems for assigning the planets' pitches, fed into the synth or used to resample the embedded notes
+use std::str::FromStr;
+
+use crate::model::Planet;
+
+/// Just major scale, repeated an octave up past the seventh degree
+const JUST_MAJOR: [f64; 7] = [
+    1.0,
+    9.0 / 8.0,
+    5.0 / 4.0,
+    4.0 / 3.0,
+    3.0 / 2.0,
+    5.0 / 3.0,
+    15.0 / 8.0,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tuning {
+    /// planet `i` plays harmonic `i + 1`, the pure version of what the embedded notes approximate
+    Harmonic,
+    /// the harmonic series rounded to the nearest equal tempered semitone, which is what the embedded notes are
+    Equal,
+    /// planet `i` plays degree `i` of a just intonation major scale
+    Just,
+    /// Kepler's Harmonices Mundi idea: pitch follows orbital speed, every planet a folded-down interval above the next slower one
+    Kepler,
+}
+
+impl FromStr for Tuning {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "harmonic" => Ok(Tuning::Harmonic),
+            "equal" => Ok(Tuning::Equal),
+            "just" => Ok(Tuning::Just),
+            "kepler" => Ok(Tuning::Kepler),
+            _ => Err(format!(
+                "unknown tuning '{}', expected harmonic, equal, just or kepler",
+                name
+            )),
+        }
+    }
+}
+
+/// Brings a ratio into `1.0..2.0` by whole octaves
+fn fold(ratio: f64) -> f64 {
+    ratio / 2f64.powf(ratio.log2().floor())
+}
+
+/// Harmonic `n` rounded to the nearest equal tempered semitone
+pub fn equal_tempered(n: u32) -> f64 {
+    2f64.powf((12.0 * (n as f64).log2()).round() / 12.0)
+}
+
+impl Tuning {
+    /// Pitch of every planet as a ratio to the fundamental, `periods` being their orbital periods in order.
+    /// Only Kepler tuning looks at the periods, the rest go by position.
+    pub fn ratios(self, periods: &[f64]) -> Vec<f64> {
+        match self {
+            Tuning::Harmonic => (1..=periods.len()).map(|n| n as f64).collect(),
+            Tuning::Equal => (1..=periods.len() as u32).map(equal_tempered).collect(),
+            Tuning::Just => (0..periods.len())
+                .map(|i| JUST_MAJOR[i % 7] * 2f64.powi((i / 7) as i32))
+                .collect(),
+            Tuning::Kepler => {
+                let mut slowest_first: Vec<usize> = (0..periods.len()).collect();
+                slowest_first.sort_by(|&a, &b| periods[b].total_cmp(&periods[a]));
+                let mut ratios = vec![1.0; periods.len()];
+                for pair in slowest_first.windows(2) {
+                    let (slower, faster) = (pair[0], pair[1]);
+                    ratios[faster] = ratios[slower] * fold(periods[slower] / periods[faster]);
+                }
+                ratios
+            }
+        }
+    }
+}
+
+/// Plays `samples` back `factor` times faster with linear interpolation, raising the pitch by `factor`
+pub fn resample(samples: &[f32], factor: f64) -> Result<Vec<f32>, String> {
+    if !(factor.is_finite() && factor > 0.0) {
+        return Err(format!("can't resample by a factor of {}", factor));
+    }
+    let length = (samples.len() as f64 / factor).floor() as usize;
+    Ok((0..length)
+        .map(|i| {
+            let at = i as f64 * factor;
+            let index = at as usize;
+            let fraction = (at - index as f64) as f32;
+            let a = samples[index];
+            let b = samples.get(index + 1).copied().unwrap_or(0.0);
+            a + (b - a) * fraction
+        })
+        .collect())
+}
+
+/// Retunes every planet to `tuning` on top of `fundamental` Hz, see [`crate::model::Sample::retuned`] for what happens to each kind of sample
+pub fn retune(planets: &mut [Planet], tuning: Tuning, fundamental: f64) {
+    let periods: Vec<f64> = planets.iter().map(|planet| planet.period()).collect();
+    for (planet, ratio) in planets.iter_mut().zip(tuning.ratios(&periods)) {
+        planet.sample = planet.sample.retuned(fundamental * ratio);
+    }
+}
@@ -0,0 +1,162 @@
+/// Voice allocation, how many notes can ring at once and which one gives way when there's no room left
+use std::str::FromStr;
+
+/// What the single threaded player used to allocate as SDL_mixer channels
+pub const DEFAULT_VOICES: usize = 100;
+/// Frames per [`Loudness`] window
+const WINDOW: usize = 1024;
+
+/// Which ringing note makes room for a new one
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Steal {
+    /// the note that's been ringing longest
+    #[default]
+    Oldest,
+    /// the note that's quietest right now
+    Quietest,
+    /// the new note's planet's own oldest note, and if it has none ringing the new note is dropped
+    SamePlanet,
+}
+
+impl FromStr for Steal {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "oldest" => Ok(Steal::Oldest),
+            "quietest" => Ok(Steal::Quietest),
+            "planet" | "same-planet" => Ok(Steal::SamePlanet),
+            _ => Err(format!(
+                "unknown steal policy '{}', expected oldest, quietest or planet",
+                name
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Polyphony {
+    /// notes ringing at once across every planet
+    pub voices: usize,
+    /// notes ringing at once for any one planet, unlimited if `None`
+    pub per_planet: Option<usize>,
+    pub steal: Steal,
+}
+
+impl Default for Polyphony {
+    fn default() -> Self {
+        Self {
+            voices: DEFAULT_VOICES,
+            per_planet: None,
+            steal: Steal::Oldest,
+        }
+    }
+}
+
+/// A note that's already ringing, as far as choosing one to steal goes
+#[derive(Debug, Clone, Copy)]
+pub struct Ringing {
+    pub planet: usize,
+    /// seconds since it started
+    pub age: f64,
+    /// roughly how loud it is at the moment, see [`Loudness`]
+    pub level: f32,
+}
+
+/// What to do with a new note
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admit {
+    /// there's room, just play it
+    Play,
+    /// cut the ringing note at this index to make room
+    Steal(usize),
+    /// no room and nothing the policy is allowed to steal
+    Drop,
+}
+
+impl Polyphony {
+    /// Decides whether a new note for `planet` fits next to `ringing`
+    pub fn admit(&self, planet: usize, ringing: &[Ringing]) -> Admit {
+        let own = || {
+            ringing
+                .iter()
+                .enumerate()
+                .filter(move |(_, note)| note.planet == planet)
+        };
+        let planet_full = self.per_planet.is_some_and(|limit| own().count() >= limit);
+        let candidate = if planet_full {
+            // it's the planet that's out of room, so only its own notes are fair game
+            match self.steal {
+                Steal::Quietest => own().min_by(|a, b| a.1.level.total_cmp(&b.1.level)),
+                Steal::Oldest | Steal::SamePlanet => {
+                    own().max_by(|a, b| a.1.age.total_cmp(&b.1.age))
+                }
+            }
+        } else if ringing.len() >= self.voices {
+            match self.steal {
+                Steal::Oldest => ringing
+                    .iter()
+                    .enumerate()
+                    .max_by(|a, b| a.1.age.total_cmp(&b.1.age)),
+                Steal::Quietest => ringing
+                    .iter()
+                    .enumerate()
+                    .min_by(|a, b| a.1.level.total_cmp(&b.1.level)),
+                Steal::SamePlanet => own().max_by(|a, b| a.1.age.total_cmp(&b.1.age)),
+            }
+        } else {
+            return Admit::Play;
+        };
+        match candidate {
+            Some((index, _)) => Admit::Steal(index),
+            None => Admit::Drop,
+        }
+    }
+}
+
+/// Counts of what the allocator did, reported instead of crashing when the voices run out
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct VoiceStats {
+    pub played: u64,
+    pub stolen: u64,
+    pub dropped: u64,
+    /// most notes ringing at once
+    pub peak: usize,
+}
+
+impl VoiceStats {
+    /// Counts `admit`, with `ringing` notes playing after it was carried out
+    pub fn record(&mut self, admit: Admit, ringing: usize) {
+        match admit {
+            Admit::Play => self.played += 1,
+            Admit::Steal(_) => {
+                self.played += 1;
+                self.stolen += 1;
+            }
+            Admit::Drop => self.dropped += 1,
+        }
+        self.peak = self.peak.max(ringing);
+    }
+}
+
+/// Peak level of a sample over short windows, so the quietest ringing note can be found without scanning samples
+#[derive(Debug, Clone)]
+pub struct Loudness {
+    peaks: Vec<f32>,
+}
+
+impl Loudness {
+    pub fn new(samples: &[f32]) -> Self {
+        Self {
+            peaks: samples
+                .chunks(WINDOW)
+                .map(|window| window.iter().fold(0.0, |peak, s| s.abs().max(peak)))
+                .collect(),
+        }
+    }
+
+    /// Level around `frame`, 0 once the sample has ended
+    pub fn at(&self, frame: usize) -> f32 {
+        self.peaks.get(frame / WINDOW).copied().unwrap_or(0.0)
+    }
+}
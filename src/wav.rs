@@ -0,0 +1,188 @@
+/// Tiny RIFF/WAVE reader and writer, just enough for the embedded notes and offline renders
+use std::io::{Seek, SeekFrom, Write};
+
+/// Decoded PCM, downmixed to mono and normalized to `-1.0..=1.0`
+#[derive(Debug, Clone)]
+pub struct Pcm {
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+}
+
+/// Sample encoding used when writing a WAV file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleFormat {
+    /// 16-bit signed integer PCM
+    I16,
+    /// 32-bit IEEE float
+    F32,
+}
+
+impl SampleFormat {
+    fn bits(self) -> u16 {
+        match self {
+            SampleFormat::I16 => 16,
+            SampleFormat::F32 => 32,
+        }
+    }
+
+    fn tag(self) -> u16 {
+        match self {
+            SampleFormat::I16 => 1,
+            SampleFormat::F32 => 3,
+        }
+    }
+}
+
+/// WAVE_FORMAT_EXTENSIBLE, where the actual tag is the start of the sub-format GUID
+const EXTENSIBLE: u16 = 0xFFFE;
+/// The rest of that GUID, the same for every sub-format that has a plain tag too
+const GUID_TAIL: [u8; 14] = [
+    0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+];
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+/// Decodes 8/16/24/32-bit integer or 32-bit float PCM WAV data
+pub fn decode(bytes: &[u8]) -> Result<Pcm, String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("not a RIFF/WAVE file".to_string());
+    }
+
+    let mut format = None;
+    let mut data = None;
+    let mut at = 12;
+    while at + 8 <= bytes.len() {
+        let id = &bytes[at..at + 4];
+        let size = u32_at(bytes, at + 4) as usize;
+        let body = &bytes[at + 8..(at + 8 + size).min(bytes.len())];
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let tag = match u16_at(body, 0) {
+                    EXTENSIBLE if body.len() >= 40 && body[26..40] == GUID_TAIL => u16_at(body, 24),
+                    tag => tag,
+                };
+                format = Some((tag, u16_at(body, 2), u32_at(body, 4), u16_at(body, 14)))
+            }
+            b"data" if body.len() < size => {
+                return Err(format!(
+                    "data chunk is cut short, {} of {} bytes are there",
+                    body.len(),
+                    size
+                ))
+            }
+            b"data" => data = Some(body),
+            _ => {}
+        }
+        // chunks are padded to an even size
+        at += 8 + size + (size & 1);
+    }
+
+    let (tag, channels, sample_rate, bits) = format.ok_or("missing fmt chunk")?;
+    let data = data.ok_or("missing data chunk")?;
+    if channels == 0 {
+        return Err("fmt chunk declares zero channels".to_string());
+    }
+    if sample_rate == 0 {
+        return Err("fmt chunk declares a sample rate of zero".to_string());
+    }
+
+    let read: fn(&[u8]) -> f32 = match (tag, bits) {
+        (1, 8) => |b| (b[0] as f32 - 128.0) / 128.0,
+        (1, 16) => |b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+        (1, 24) => |b| i32::from_le_bytes([0, b[0], b[1], b[2]]) as f32 / 2_147_483_648.0,
+        (1, 32) => |b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2_147_483_648.0,
+        (3, 32) => |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        _ => {
+            return Err(format!(
+                "unsupported WAV encoding (tag {}, {} bits)",
+                tag, bits
+            ))
+        }
+    };
+
+    let width = bits as usize / 8;
+    let frame = width * channels as usize;
+    let samples = data
+        .chunks_exact(frame)
+        .map(|f| f.chunks_exact(width).map(read).sum::<f32>() / channels as f32)
+        .collect();
+
+    Ok(Pcm {
+        sample_rate,
+        samples,
+    })
+}
+
+/// Streams interleaved `f32` frames into a WAV file, patching the header sizes on [`WavWriter::finish`]
+pub struct WavWriter<W: Write + Seek> {
+    out: W,
+    format: SampleFormat,
+    data_len: u32,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(
+        mut out: W,
+        sample_rate: u32,
+        channels: u16,
+        format: SampleFormat,
+    ) -> Result<Self, String> {
+        let block_align = channels * format.bits() / 8;
+        let mut header = Vec::with_capacity(44);
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(b"WAVEfmt ");
+        header.extend_from_slice(&16u32.to_le_bytes());
+        header.extend_from_slice(&format.tag().to_le_bytes());
+        header.extend_from_slice(&channels.to_le_bytes());
+        header.extend_from_slice(&sample_rate.to_le_bytes());
+        header.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+        header.extend_from_slice(&block_align.to_le_bytes());
+        header.extend_from_slice(&format.bits().to_le_bytes());
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&0u32.to_le_bytes());
+        out.write_all(&header).map_err(|e| e.to_string())?;
+        Ok(Self {
+            out,
+            format,
+            data_len: 0,
+        })
+    }
+
+    /// Writes interleaved samples, clamping when the target is integer PCM
+    pub fn write(&mut self, samples: &[f32]) -> Result<(), String> {
+        let mut bytes = Vec::with_capacity(samples.len() * self.format.bits() as usize / 8);
+        for &s in samples {
+            match self.format {
+                SampleFormat::I16 => bytes.extend_from_slice(
+                    &((s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes(),
+                ),
+                SampleFormat::F32 => bytes.extend_from_slice(&s.to_le_bytes()),
+            }
+        }
+        self.out.write_all(&bytes).map_err(|e| e.to_string())?;
+        self.data_len = self
+            .data_len
+            .checked_add(bytes.len() as u32)
+            .ok_or("WAV data exceeds 4 GiB")?;
+        Ok(())
+    }
+
+    /// Fills in the RIFF and data chunk sizes and flushes the output
+    pub fn finish(mut self) -> Result<W, String> {
+        let patch = |out: &mut W, at: u64, value: u32| -> std::io::Result<()> {
+            out.seek(SeekFrom::Start(at))?;
+            out.write_all(&value.to_le_bytes())
+        };
+        patch(&mut self.out, 4, 36 + self.data_len).map_err(|e| e.to_string())?;
+        patch(&mut self.out, 40, self.data_len).map_err(|e| e.to_string())?;
+        self.out.flush().map_err(|e| e.to_string())?;
+        Ok(self.out)
+    }
+}
@@ -0,0 +1,99 @@
+use mandala::midi::{channel, export_midi, note_and_bend, write_varlen, MAX_PLANETS};
+use mandala::model::solar_system;
+use mandala::synth::FUNDAMENTAL;
+
+fn varlen(value: u32) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_varlen(&mut out, value);
+    out
+}
+
+/// Big endian number out of `bytes`
+fn be(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |n, &b| n << 8 | b as u32)
+}
+
+#[test]
+fn varlens_split_into_seven_bit_groups() {
+    assert_eq!(varlen(0), [0x00]);
+    assert_eq!(varlen(0x7F), [0x7F]);
+    assert_eq!(varlen(0x80), [0x81, 0x00]);
+    assert_eq!(varlen(0x2000), [0xC0, 0x00]);
+    assert_eq!(varlen(0x3FFF), [0xFF, 0x7F]);
+    assert_eq!(varlen(0x4000), [0x81, 0x80, 0x00]);
+    assert_eq!(varlen(0x0FFF_FFFF), [0xFF, 0xFF, 0xFF, 0x7F]);
+}
+
+#[test]
+fn exact_notes_need_no_bend() {
+    assert_eq!(note_and_bend(440.0), (69, 8192));
+    // the fundamental is a B, so every octave of it lands right on a note
+    for (harmonic, note) in [(1, 35), (2, 47), (4, 59), (8, 71), (16, 83)] {
+        assert_eq!(
+            note_and_bend(FUNDAMENTAL as f64 * harmonic as f64),
+            (note, 8192),
+            "harmonic {}",
+            harmonic
+        );
+    }
+    // the third harmonic is a couple of cents sharp of F#
+    let (note, bend) = note_and_bend(FUNDAMENTAL as f64 * 3.0);
+    assert_eq!(note, 54);
+    assert!(bend > 8192 && bend < 8192 + 100, "bend {}", bend);
+}
+
+#[test]
+fn planets_skip_the_drum_channel() {
+    let channels: Vec<u8> = (0..MAX_PLANETS).map(|i| channel(i).unwrap()).collect();
+    assert_eq!(
+        channels,
+        [0, 1, 2, 3, 4, 5, 6, 7, 8, 10, 11, 12, 13, 14, 15]
+    );
+    assert!(channel(MAX_PLANETS).is_err());
+}
+
+#[test]
+fn chunk_lengths_match_what_follows() {
+    let planets = solar_system();
+    let path = std::env::temp_dir().join("mandala-chunk-lengths.mid");
+    export_midi(&planets, path.to_str().unwrap(), 60.0).unwrap();
+    let smf = std::fs::read(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    assert_eq!(&smf[..4], b"MThd");
+    assert_eq!(be(&smf[4..8]), 6);
+    // type 1, a tempo track and then one per planet, 960 ticks per quarter
+    assert_eq!(be(&smf[8..10]), 1);
+    assert_eq!(be(&smf[10..12]) as usize, planets.len() + 1);
+    assert_eq!(be(&smf[12..14]), 960);
+
+    let mut rest = &smf[14..];
+    let mut tracks = 0;
+    while !rest.is_empty() {
+        assert_eq!(&rest[..4], b"MTrk", "track {}", tracks);
+        let length = be(&rest[4..8]) as usize;
+        let data = &rest[8..8 + length];
+        assert_eq!(
+            &data[length - 4..],
+            [0x00, 0xFF, 0x2F, 0x00],
+            "track {}",
+            tracks
+        );
+        rest = &rest[8 + length..];
+        tracks += 1;
+    }
+    assert_eq!(tracks, planets.len() + 1);
+}
+
+#[test]
+fn too_many_planets_for_midi_is_an_error() {
+    let planets: Vec<_> = solar_system()
+        .into_iter()
+        .cycle()
+        .take(MAX_PLANETS + 1)
+        .collect();
+    let path = std::env::temp_dir().join("mandala-too-many-planets.mid");
+    let result = export_midi(&planets, path.to_str().unwrap(), 1.0);
+    assert!(result.is_err());
+    assert!(!path.exists());
+}
@@ -0,0 +1,59 @@
+use mandala::mixer::{Mixer, Strip, MAX_GAIN};
+
+fn strip(gain: f32, pan: f32) -> Strip {
+    Strip {
+        gain,
+        pan,
+        muted: false,
+    }
+}
+
+#[test]
+fn solo_overrides_mute() {
+    let mut mixer = Mixer::new(vec![Strip::default(); 3]);
+    mixer.toggle_mute(1);
+    assert!(!mixer.audible(1));
+    mixer.toggle_solo(1);
+    assert!(mixer.audible(1));
+    assert!(!mixer.audible(0));
+    assert!(!mixer.audible(2));
+    assert_eq!(mixer.gains(0), (0.0, 0.0));
+    assert_eq!(mixer.gains(1), (1.0, 1.0));
+}
+
+#[test]
+fn soloing_the_soloed_planet_again_unsolos_it() {
+    let mut mixer = Mixer::new(vec![Strip::default(); 3]);
+    mixer.toggle_mute(2);
+    mixer.toggle_solo(0);
+    mixer.toggle_solo(1);
+    assert_eq!(mixer.solo(), Some(1));
+    mixer.toggle_solo(1);
+    assert_eq!(mixer.solo(), None);
+    // back to the mutes deciding
+    assert!(mixer.audible(0));
+    assert!(mixer.audible(1));
+    assert!(!mixer.audible(2));
+    // out of range planets are ignored
+    mixer.toggle_solo(3);
+    assert_eq!(mixer.solo(), None);
+}
+
+#[test]
+fn centered_strips_sound_like_the_unpanned_mix() {
+    for gain in [0.0, 0.5, 1.0, MAX_GAIN] {
+        assert_eq!(strip(gain, 0.0).stereo(), (gain, gain));
+    }
+    let mut mixer = Mixer::new(vec![strip(0.8, 0.0)]);
+    mixer.set_master(0.5);
+    assert_eq!(mixer.gains(0), (0.4, 0.4));
+}
+
+#[test]
+fn panning_only_turns_the_far_side_down() {
+    assert_eq!(strip(0.8, -1.0).stereo(), (0.8, 0.0));
+    assert_eq!(strip(0.8, 1.0).stereo(), (0.0, 0.8));
+    assert_eq!(strip(1.0, 0.5).stereo(), (0.5, 1.0));
+    // past hard left or right is still just hard left or right
+    assert_eq!(strip(1.0, -3.0).stereo(), (1.0, 0.0));
+}
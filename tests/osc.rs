@@ -0,0 +1,94 @@
+use std::net::UdpSocket;
+use std::time::Duration;
+
+use mandala::events::NoteEvent;
+use mandala::model::solar_system;
+use mandala::osc::{message, Arg, OscSender};
+
+/// A socket on a free local port for an [`OscSender`] to send to
+fn listener() -> (UdpSocket, String) {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let target = socket.local_addr().unwrap().to_string();
+    (socket, target)
+}
+
+fn receive(socket: &UdpSocket) -> Vec<u8> {
+    let mut buf = [0; 1024];
+    let len = socket.recv(&mut buf).unwrap();
+    buf[..len].to_vec()
+}
+
+/// `planet`'s note right on time at the start of `revolution`
+fn on_time(planet: usize, revolution: u64, period: f64) -> NoteEvent {
+    NoteEvent {
+        planet,
+        revolution,
+        scheduled: revolution as f64 * period,
+        actual: revolution as f64 * period,
+        voice: None,
+        speed: 1.0,
+    }
+}
+
+#[test]
+fn strings_are_null_terminated_and_padded_to_four_bytes() {
+    let packet = message("/abc", &[Arg::Str("abcdefg".to_string()), Arg::Int(-2)]);
+    let mut expected = b"/abc\0\0\0\0,si\0abcdefg\0".to_vec();
+    expected.extend_from_slice(&(-2i32).to_be_bytes());
+    assert_eq!(packet, expected);
+}
+
+#[test]
+fn triggers_go_out_as_angle_revolution_and_seconds() {
+    let planets = solar_system();
+    let (socket, target) = listener();
+    let sender = OscSender::connect(&target, 1.0, &planets).unwrap();
+
+    // 20 bytes, so a whole word of padding for the null
+    let mars = planets.iter().position(|p| p.name == "Mars").unwrap();
+    let note = on_time(mars, 3, planets[mars].period());
+    sender.trigger(&note);
+    let mut expected = b"/mandala/planet/mars\0\0\0\0,fif\0\0\0\0".to_vec();
+    expected.extend_from_slice(&0f32.to_be_bytes());
+    expected.extend_from_slice(&3i32.to_be_bytes());
+    expected.extend_from_slice(&(note.actual as f32).to_be_bytes());
+    assert_eq!(receive(&socket), expected);
+
+    // 21 bytes, padded to 24
+    let earth = planets.iter().position(|p| p.name == "Earth").unwrap();
+    let note = on_time(earth, 1, planets[earth].period());
+    sender.trigger(&note);
+    let mut expected = b"/mandala/planet/earth\0\0\0,fif\0\0\0\0".to_vec();
+    expected.extend_from_slice(&0f32.to_be_bytes());
+    expected.extend_from_slice(&1i32.to_be_bytes());
+    expected.extend_from_slice(&(note.actual as f32).to_be_bytes());
+    assert_eq!(receive(&socket), expected);
+}
+
+#[test]
+fn positions_go_out_for_every_planet_once_an_interval() {
+    let planets = solar_system();
+    let (socket, target) = listener();
+    let mut sender = OscSender::connect(&target, 60.0, &planets).unwrap();
+    sender.update(0.0);
+    // well within the interval, so nothing more goes out
+    sender.update(1.0);
+    for planet in &planets {
+        let packet = receive(&socket);
+        let address = format!("/mandala/planet/{}/position", planet.name.to_lowercase());
+        assert_eq!(&packet[..address.len()], address.as_bytes());
+    }
+    socket.set_nonblocking(true).unwrap();
+    assert!(socket.recv(&mut [0; 1024]).is_err());
+}
+
+#[test]
+fn intervals_have_to_be_a_duration() {
+    let planets = solar_system();
+    let (_socket, target) = listener();
+    assert!(OscSender::connect(&target, f64::INFINITY, &planets).is_err());
+    assert!(OscSender::connect(&target, -1.0, &planets).is_err());
+}
@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use mandala::sequencer::{Sequencer, Track, CHANNELS, SAMPLE_RATE};
+use mandala::simulation::Simulation;
+
+/// A sequencer whose tracks are single clicks, so every note shows up as exactly one non-silent frame
+fn clicks(periods: &[f64]) -> Sequencer {
+    let tracks = periods
+        .iter()
+        .map(|&period| Track {
+            period,
+            samples: Arc::from(vec![1.0]),
+        })
+        .collect();
+    Sequencer::new(tracks)
+}
+
+/// Renders `frames` frames in blocks of `block` and returns the frames notes start on
+fn onsets(sequencer: &mut Sequencer, frames: usize, block: usize) -> Vec<usize> {
+    let mut onsets = Vec::new();
+    let mut rendered = 0;
+    while rendered < frames {
+        let len = block.min(frames - rendered);
+        let mut out = vec![0.0; len * CHANNELS];
+        sequencer.fill(&mut out);
+        for (i, frame) in out.chunks_exact(CHANNELS).enumerate() {
+            if frame[0] != 0.0 {
+                onsets.push(rendered + i);
+            }
+        }
+        rendered += len;
+    }
+    onsets
+}
+
+/// The frames the simulation's triggers in the first `frames` frames round to
+fn due(periods: &[f64], frames: usize) -> Vec<usize> {
+    let mut simulation = Simulation::new(periods.to_vec());
+    let mut due: Vec<usize> = simulation
+        .advance(frames as f64 / SAMPLE_RATE as f64)
+        .iter()
+        .map(|trigger| (trigger.seconds * SAMPLE_RATE as f64).round() as usize)
+        .filter(|&frame| frame < frames)
+        .collect();
+    due.dedup();
+    due
+}
+
+#[test]
+fn notes_start_on_the_frame_their_trigger_is_due() {
+    let periods = [0.01, 0.0137];
+    let expected = due(&periods, SAMPLE_RATE as usize);
+    assert_eq!(&expected[..4], &[0, 441, 604, 882]);
+    for block in [1, 64, 441, 1000, 4096] {
+        let mut sequencer = clicks(&periods);
+        assert_eq!(
+            onsets(&mut sequencer, SAMPLE_RATE as usize, block),
+            expected,
+            "blocks of {}",
+            block
+        );
+    }
+}
+
+#[test]
+fn logged_notes_fall_on_their_scheduled_time() {
+    let mut sequencer = clicks(&[0.01, 0.0137]);
+    sequencer.log_events();
+    onsets(&mut sequencer, SAMPLE_RATE as usize, 512);
+    let events = sequencer.take_events();
+    assert_eq!(events.len(), 100 + 73);
+    for event in events {
+        assert!(
+            (event.actual - event.scheduled).abs() <= 1.0 / SAMPLE_RATE as f64,
+            "{:?}",
+            event
+        );
+    }
+}
+
+#[test]
+fn double_speed_starts_notes_twice_as_often() {
+    let mut sequencer = clicks(&[0.01]);
+    sequencer.set_speed(2.0);
+    // piece frame 441 is reached halfway through output frame 220, so the note starts on the next one
+    assert_eq!(
+        &onsets(&mut sequencer, 1000, 256)[..],
+        &[0, 221, 441, 662, 882]
+    );
+}
+
+#[test]
+fn seeking_starts_the_next_note_where_its_trigger_is_due() {
+    let mut sequencer = clicks(&[0.01]);
+    sequencer.seek(0.015);
+    // 0.02s in is 0.005s, or 220.5 frames, after where playback picks up
+    assert_eq!(&onsets(&mut sequencer, 1000, 300)[..], &[221, 662]);
+}
+
+#[test]
+fn paused_sequencer_starts_no_notes() {
+    let mut sequencer = clicks(&[0.01]);
+    sequencer.set_paused(true);
+    assert!(onsets(&mut sequencer, 2000, 512).is_empty());
+    sequencer.set_paused(false);
+    assert_eq!(&onsets(&mut sequencer, 1000, 512)[..], &[0, 441, 882]);
+}
+
+#[test]
+fn nothing_is_triggered_past_the_end() {
+    let mut sequencer = clicks(&[0.01]);
+    sequencer.set_end(Some(0.02));
+    assert_eq!(&onsets(&mut sequencer, 4000, 512)[..], &[0, 441, 882]);
+}
+
+#[test]
+fn notes_ringing_at_the_end_play_out() {
+    let mut sequencer = Sequencer::new(vec![Track {
+        period: 0.01,
+        samples: Arc::from(vec![1.0; 1000]),
+    }]);
+    sequencer.set_end(Some(0.0));
+    let mut out = vec![0.0; 2000 * CHANNELS];
+    sequencer.fill(&mut out);
+    let sounding = out.chunks_exact(CHANNELS).filter(|f| f[0] != 0.0).count();
+    assert_eq!(sounding, 1000);
+}
@@ -0,0 +1,183 @@
+use std::f64::consts::TAU;
+use std::path::Path;
+
+use mandala::alignment::{self, TOLERANCE};
+use mandala::model::Planet;
+use mandala::simulation::{Simulation, Trigger};
+use mandala::system::load_system;
+
+const HOUR: f64 = 60.0 * 60.0;
+
+fn system(name: &str) -> Vec<Planet> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("systems")
+        .join(name);
+    load_system(&path).unwrap()
+}
+
+/// Runs `simulation` for `seconds` in steps of `dt`, the last one cut short to land on `seconds` exactly
+fn run(simulation: &mut Simulation, seconds: f64, dt: f64) -> Vec<Trigger> {
+    let mut triggers = Vec::new();
+    while simulation.elapsed() < seconds {
+        let step = dt.min(seconds - simulation.elapsed());
+        triggers.extend(simulation.advance(step));
+    }
+    triggers
+}
+
+fn count(triggers: &[Trigger], planet: usize) -> usize {
+    triggers.iter().filter(|t| t.planet == planet).count()
+}
+
+#[test]
+fn triggers_fall_on_whole_periods_in_order() {
+    let mut simulation = Simulation::new(vec![1.0, 1.5]);
+    let triggers = simulation.advance(6.0);
+    let times: Vec<(usize, f64)> = triggers.iter().map(|t| (t.planet, t.seconds)).collect();
+    assert_eq!(
+        times,
+        [
+            (0, 0.0),
+            (1, 0.0),
+            (0, 1.0),
+            (1, 1.5),
+            (0, 2.0),
+            (0, 3.0),
+            (1, 3.0),
+            (0, 4.0),
+            (1, 4.5),
+            (0, 5.0),
+        ]
+    );
+    // the one right at the end of the step belongs to the next
+    let next = simulation.advance(0.5);
+    assert_eq!(next.len(), 2);
+    assert!(next.iter().all(|t| t.seconds == 6.0));
+}
+
+#[test]
+fn trigger_counts_over_an_hour_dont_depend_on_the_step() {
+    let planets = system("solar_system.toml");
+    for dt in [1.0 / 60.0, 0.37, 10.0, HOUR] {
+        let mut simulation = Simulation::for_planets(&planets);
+        let triggers = run(&mut simulation, HOUR, dt);
+        for (i, planet) in planets.iter().enumerate() {
+            let expected = (HOUR / planet.period()).ceil() as usize;
+            assert_eq!(
+                count(&triggers, i),
+                expected,
+                "{} with {}s steps",
+                planet.name,
+                dt
+            );
+        }
+    }
+}
+
+#[test]
+fn consecutive_triggers_are_one_period_apart() {
+    let planets = system("solar_system.toml");
+    let mut simulation = Simulation::for_planets(&planets);
+    let triggers = run(&mut simulation, 3.0 * HOUR, 1.0 / 60.0);
+    assert!(
+        triggers.windows(2).all(|w| w[0].seconds <= w[1].seconds),
+        "triggers out of order"
+    );
+    for (i, planet) in planets.iter().enumerate() {
+        let own: Vec<&Trigger> = triggers.iter().filter(|t| t.planet == i).collect();
+        assert_eq!(own[0].revolution, 0);
+        for pair in own.windows(2) {
+            assert_eq!(pair[1].revolution, pair[0].revolution + 1);
+            let gap = pair[1].seconds - pair[0].seconds;
+            assert!(
+                (gap - planet.period()).abs() < 1e-6,
+                "{}: {}s between triggers, period is {}s",
+                planet.name,
+                gap,
+                planet.period()
+            );
+        }
+    }
+}
+
+#[test]
+fn revolutions_and_angles_follow_elapsed_time() {
+    let planets = system("solar_system.toml");
+    let mut simulation = Simulation::for_planets(&planets);
+    let mut played = vec![0u64; planets.len()];
+    while simulation.elapsed() < 2.0 * HOUR {
+        for trigger in simulation.advance(0.7) {
+            played[trigger.planet] += 1;
+        }
+        for (i, &played) in played.iter().enumerate() {
+            // every revolution completed, plus the note at the start
+            let expected = simulation.revolutions(i) + 1;
+            assert!(
+                played == expected || (played == expected - 1 && simulation.until_next(i) == 0.0),
+                "{} played {} notes after {} revolutions",
+                planets[i].name,
+                played,
+                simulation.revolutions(i)
+            );
+            let angle = simulation.angle(i);
+            assert!((0.0..TAU).contains(&angle), "angle {} out of range", angle);
+            assert!(simulation.until_next(i) <= planets[i].period());
+        }
+    }
+}
+
+#[test]
+fn seeking_picks_up_at_the_next_trigger() {
+    let mut simulation = Simulation::new(vec![2.0, 3.0]);
+    simulation.advance(5.0);
+    simulation.seek(12.5);
+    let triggers = simulation.advance(3.0);
+    let times: Vec<(usize, f64)> = triggers.iter().map(|t| (t.planet, t.seconds)).collect();
+    assert_eq!(times, [(0, 14.0), (1, 15.0)]);
+    assert_eq!(triggers[0].revolution, 7);
+
+    // landing right on a trigger still plays it
+    simulation.seek(6.0);
+    let triggers = simulation.advance(0.1);
+    assert_eq!(triggers.len(), 2);
+    assert!(triggers.iter().all(|t| t.seconds == 6.0));
+}
+
+#[test]
+fn galilean_moons_align_when_predicted() {
+    let planets = system("galilean_moons.json");
+    let end = alignment::predicted_duration(&planets).expect("the moons align");
+    assert!(end > HOUR, "aligned after only {}s", end);
+
+    let mut simulation = Simulation::for_planets(&planets);
+    let triggers = run(&mut simulation, end + 1.0, 1.0 / 60.0);
+    for (i, planet) in planets.iter().enumerate() {
+        let closest = triggers
+            .iter()
+            .filter(|t| t.planet == i)
+            .map(|t| (t.seconds - end).abs())
+            .fold(f64::INFINITY, f64::min);
+        assert!(
+            closest <= TOLERANCE,
+            "{}'s closest note is {}s from the alignment",
+            planet.name,
+            closest
+        );
+    }
+
+    simulation.seek(end);
+    assert!(simulation.aligned(TOLERANCE));
+    // halfway there the slowest moon is on the far side of its orbit
+    simulation.seek(end - planets.iter().map(Planet::period).fold(0.0, f64::max) / 2.0);
+    assert!(!simulation.aligned(TOLERANCE));
+}
+
+#[test]
+fn alignment_search_gives_up_on_short_periods() {
+    // these never line up exactly, without the cap it'd check 2.7 billion beats before reaching the horizon
+    let periods = [0.001, 0.001 * std::f64::consts::PI];
+    assert_eq!(
+        alignment::alignment_time(&periods, 0.0, alignment::HORIZON),
+        None
+    );
+}
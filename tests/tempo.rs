@@ -0,0 +1,73 @@
+use std::path::Path;
+
+use mandala::model::{solar_system, solar_system_at};
+use mandala::rotation_around_sun_days::{
+    EARTH_FACTOR, FACTORS, MERCURY_FACTOR, ORIGINAL_BPMS, PLUTO_FACTOR,
+};
+use mandala::system::load_system;
+use mandala::tempo::{bpm_from_period, Tempo, ORIGINAL_TEMPO};
+
+/// The original tempos were typed in by hand, they only need to agree to a couple per mille
+const TOLERANCE: f32 = 0.002;
+
+#[test]
+fn original_bpms_match_orbital_periods() {
+    let derived = ORIGINAL_TEMPO.bpms(&FACTORS);
+    for (i, (&original, derived)) in ORIGINAL_BPMS.iter().zip(derived).enumerate() {
+        let error = (original - derived).abs() / derived;
+        assert!(
+            error < TOLERANCE,
+            "planet #{}: original {} bpm, derived {} bpm ({:.3}% off)",
+            i + 1,
+            original,
+            derived,
+            error * 100.0
+        );
+    }
+}
+
+#[test]
+fn reference_planet_keeps_its_tempo() {
+    assert_eq!(bpm_from_period(EARTH_FACTOR, EARTH_FACTOR, 30.0), 30.0);
+    // twice the period, half the tempo
+    assert_eq!(
+        bpm_from_period(2.0 * EARTH_FACTOR, EARTH_FACTOR, 30.0),
+        15.0
+    );
+}
+
+#[test]
+fn length_fits_one_revolution_of_the_slowest_orbit() {
+    let bpms = Tempo::Length(600.0).bpms(&FACTORS);
+    let pluto = bpms[8];
+    assert!((60.0 / pluto - 600.0).abs() < 1e-3);
+
+    // ratios between planets don't depend on the chosen length
+    let mercury = bpms[0];
+    let ratio = mercury / pluto;
+    assert!((ratio - PLUTO_FACTOR / MERCURY_FACTOR).abs() / ratio < 1e-5);
+}
+
+#[test]
+fn solar_system_plays_the_derived_tempos() {
+    let derived = ORIGINAL_TEMPO.bpms(&FACTORS);
+    let bpms: Vec<f32> = solar_system().iter().map(|planet| planet.bpm).collect();
+    assert_eq!(bpms, derived);
+
+    let planets = solar_system_at(Tempo::earth(60.0));
+    assert_eq!(planets[2].bpm, 60.0);
+    assert!((planets[0].bpm / derived[0] - 2.0).abs() < 1e-5);
+}
+
+#[test]
+fn solar_system_file_is_the_default_system() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("systems/solar_system.toml");
+    let file = load_system(&path).unwrap();
+    let default = solar_system();
+    assert_eq!(file.len(), default.len());
+    for (file, default) in file.iter().zip(&default) {
+        assert_eq!(file.name, default.name);
+        // exactly, alignments depend on the ratios between periods
+        assert_eq!(file.bpm, default.bpm, "{}", file.name);
+    }
+}
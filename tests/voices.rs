@@ -0,0 +1,119 @@
+use mandala::voices::{Admit, Loudness, Polyphony, Ringing, Steal, VoiceStats};
+
+fn ringing(planet: usize, age: f64, level: f32) -> Ringing {
+    Ringing { planet, age, level }
+}
+
+fn polyphony(voices: usize, per_planet: Option<usize>, steal: Steal) -> Polyphony {
+    Polyphony {
+        voices,
+        per_planet,
+        steal,
+    }
+}
+
+/// Three notes filling three voices: planet 0's old quiet one, planet 1's loud one and planet 2's new one
+fn full() -> Vec<Ringing> {
+    vec![
+        ringing(0, 3.0, 0.5),
+        ringing(1, 5.0, 0.9),
+        ringing(2, 0.5, 0.1),
+    ]
+}
+
+#[test]
+fn room_left_means_play() {
+    let notes = full();
+    for steal in [Steal::Oldest, Steal::Quietest, Steal::SamePlanet] {
+        assert_eq!(polyphony(4, None, steal).admit(1, &notes), Admit::Play);
+        assert_eq!(polyphony(4, Some(2), steal).admit(1, &notes), Admit::Play);
+    }
+}
+
+#[test]
+fn full_voices_steal_by_policy() {
+    let notes = full();
+    assert_eq!(
+        polyphony(3, None, Steal::Oldest).admit(0, &notes),
+        Admit::Steal(1)
+    );
+    assert_eq!(
+        polyphony(3, None, Steal::Quietest).admit(0, &notes),
+        Admit::Steal(2)
+    );
+    assert_eq!(
+        polyphony(3, None, Steal::SamePlanet).admit(0, &notes),
+        Admit::Steal(0)
+    );
+}
+
+#[test]
+fn same_planet_drops_when_its_planet_has_nothing_ringing() {
+    let notes = full();
+    assert_eq!(
+        polyphony(3, None, Steal::SamePlanet).admit(3, &notes),
+        Admit::Drop
+    );
+    // the other policies take whatever they'd take from anyone
+    assert_eq!(
+        polyphony(3, None, Steal::Oldest).admit(3, &notes),
+        Admit::Steal(1)
+    );
+}
+
+#[test]
+fn planet_limit_only_steals_that_planets_notes() {
+    let notes = vec![
+        ringing(0, 2.0, 0.2),
+        ringing(1, 9.0, 0.0),
+        ringing(0, 4.0, 0.8),
+        ringing(0, 1.0, 0.6),
+    ];
+    // plenty of voices, but planet 0 already has its 3
+    assert_eq!(
+        polyphony(10, Some(3), Steal::Oldest).admit(0, &notes),
+        Admit::Steal(2)
+    );
+    assert_eq!(
+        polyphony(10, Some(3), Steal::Quietest).admit(0, &notes),
+        Admit::Steal(0)
+    );
+    assert_eq!(
+        polyphony(10, Some(3), Steal::SamePlanet).admit(0, &notes),
+        Admit::Steal(2)
+    );
+    // planet 1 is below its limit
+    assert_eq!(
+        polyphony(10, Some(3), Steal::Oldest).admit(1, &notes),
+        Admit::Play
+    );
+}
+
+#[test]
+fn stats_count_what_happened() {
+    let mut stats = VoiceStats::default();
+    stats.record(Admit::Play, 1);
+    stats.record(Admit::Play, 2);
+    stats.record(Admit::Steal(0), 2);
+    stats.record(Admit::Drop, 2);
+    stats.record(Admit::Play, 1);
+    assert_eq!(
+        stats,
+        VoiceStats {
+            played: 4,
+            stolen: 1,
+            dropped: 1,
+            peak: 2,
+        }
+    );
+}
+
+#[test]
+fn loudness_follows_the_sample_and_ends_with_it() {
+    let mut samples = vec![0.25; 1024];
+    samples.extend(vec![-0.75; 100]);
+    let loudness = Loudness::new(&samples);
+    assert_eq!(loudness.at(0), 0.25);
+    assert_eq!(loudness.at(1030), 0.75);
+    assert_eq!(loudness.at(5000), 0.0);
+}
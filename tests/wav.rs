@@ -0,0 +1,81 @@
+use mandala::wav::decode;
+
+/// A RIFF/WAVE file with `fmt` as its fmt chunk and `data` as its data chunk, declared `declared` bytes long
+fn wav(fmt: &[u8], data: &[u8], declared: u32) -> Vec<u8> {
+    let mut body = b"WAVEfmt ".to_vec();
+    body.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+    body.extend_from_slice(fmt);
+    body.extend_from_slice(b"data");
+    body.extend_from_slice(&declared.to_le_bytes());
+    body.extend_from_slice(data);
+    let mut file = b"RIFF".to_vec();
+    file.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    file.extend_from_slice(&body);
+    file
+}
+
+/// The 16 bytes every fmt chunk starts with
+fn fmt(tag: u16, channels: u16, sample_rate: u32, bits: u16) -> Vec<u8> {
+    let block_align = channels * bits / 8;
+    let mut fmt = Vec::new();
+    fmt.extend_from_slice(&tag.to_le_bytes());
+    fmt.extend_from_slice(&channels.to_le_bytes());
+    fmt.extend_from_slice(&sample_rate.to_le_bytes());
+    fmt.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    fmt.extend_from_slice(&block_align.to_le_bytes());
+    fmt.extend_from_slice(&bits.to_le_bytes());
+    fmt
+}
+
+/// The same format as WAVE_FORMAT_EXTENSIBLE with `sub_format` as the first two bytes of its GUID
+fn extensible(sub_format: u16, channels: u16, sample_rate: u32, bits: u16) -> Vec<u8> {
+    let mut fmt = fmt(0xFFFE, channels, sample_rate, bits);
+    fmt.extend_from_slice(&22u16.to_le_bytes());
+    fmt.extend_from_slice(&bits.to_le_bytes());
+    fmt.extend_from_slice(&0u32.to_le_bytes());
+    fmt.extend_from_slice(&sub_format.to_le_bytes());
+    fmt.extend_from_slice(&[
+        0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+    ]);
+    fmt
+}
+
+fn i16s(samples: &[i16]) -> Vec<u8> {
+    samples.iter().flat_map(|s| s.to_le_bytes()).collect()
+}
+
+#[test]
+fn stereo_is_downmixed_to_mono() {
+    let data = i16s(&[16384, 0, -16384, -16384]);
+    let pcm = decode(&wav(&fmt(1, 2, 22050, 16), &data, data.len() as u32)).unwrap();
+    assert_eq!(pcm.sample_rate, 22050);
+    assert_eq!(pcm.samples, [0.25, -0.5]);
+}
+
+#[test]
+fn extensible_wavs_go_by_their_sub_format() {
+    let data = i16s(&[16384, -32768]);
+    let pcm = decode(&wav(&extensible(1, 1, 44100, 16), &data, data.len() as u32)).unwrap();
+    assert_eq!(pcm.samples, [0.5, -1.0]);
+
+    let data: Vec<u8> = [0.25f32, -0.75]
+        .iter()
+        .flat_map(|s| s.to_le_bytes())
+        .collect();
+    let pcm = decode(&wav(&extensible(3, 1, 44100, 32), &data, data.len() as u32)).unwrap();
+    assert_eq!(pcm.samples, [0.25, -0.75]);
+
+    // anything else, e.g. A-law, still isn't supported
+    let data = [0u8; 4];
+    assert!(decode(&wav(&extensible(6, 1, 8000, 8), &data, 4)).is_err());
+}
+
+#[test]
+fn broken_wavs_are_errors() {
+    let data = i16s(&[0; 8]);
+    assert!(decode(&wav(&fmt(1, 1, 0, 16), &data, 16)).is_err());
+    assert!(decode(&wav(&fmt(1, 0, 44100, 16), &data, 16)).is_err());
+    // cut off halfway through the data
+    assert!(decode(&wav(&fmt(1, 1, 44100, 16), &data, 32)).is_err());
+    assert!(decode(b"RIFF\0\0\0\0WAVE").is_err());
+}